@@ -0,0 +1,132 @@
+/// Controls the strings produced by
+/// [`json_grammar_mutator_with_config`](crate::json_grammar_mutator_with_config).
+///
+/// The default configuration is the one used by
+/// [`json_grammar_mutator`](crate::json_grammar_mutator).
+///
+/// ```
+/// use fuzzcheck_json_string_generator::{json_grammar_mutator_with_config, JsonGrammarConfig};
+///
+/// let mutator = json_grammar_mutator_with_config(
+///     JsonGrammarConfig::new()
+///         .compact(true)
+///         .max_integer_digits(18)
+///         .max_fraction_digits(0)
+///         .max_exponent_digits(0),
+/// );
+/// ```
+///
+/// VS Code style JSON with comments can be generated by enabling
+/// [`comments`](Self::comments) and [`trailing_commas`](Self::trailing_commas):
+///
+/// ```
+/// use fuzzcheck_json_string_generator::{json_grammar_mutator_with_config, JsonGrammarConfig};
+///
+/// let mutator = json_grammar_mutator_with_config(
+///     JsonGrammarConfig::new().comments(true).trailing_commas(true),
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct JsonGrammarConfig {
+    pub(crate) compact: bool,
+    pub(crate) comments: bool,
+    pub(crate) trailing_commas: bool,
+    /// Whether line feeds and carriage returns are kept out of the whitespace
+    /// between tokens, for
+    /// [`ndjson_grammar_mutator`](crate::ndjson_grammar_mutator).
+    pub(crate) single_line: bool,
+    pub(crate) max_integer_digits: usize,
+    pub(crate) max_fraction_digits: usize,
+    pub(crate) max_exponent_digits: usize,
+}
+
+impl Default for JsonGrammarConfig {
+    fn default() -> Self {
+        Self {
+            compact: false,
+            comments: false,
+            trailing_commas: false,
+            single_line: false,
+            max_integer_digits: 33,
+            max_fraction_digits: 33,
+            max_exponent_digits: 2,
+        }
+    }
+}
+
+impl JsonGrammarConfig {
+    /// Creates the default configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// If `true`, no whitespace is generated between tokens (e.g.
+    /// `[1,{"a":2}]`). Otherwise (the default) any number of spaces, tabs,
+    /// line feeds and carriage returns may appear wherever RFC 8259 allows
+    /// them: before and after the document, and around brackets, braces,
+    /// commas and colons.
+    ///
+    /// This doesn't prevent [`comments`](Self::comments) from being generated.
+    pub fn compact(mut self, compact: bool) -> Self {
+        self.compact = compact;
+        self
+    }
+
+    /// If `true`, comments (`// ...` up to the end of the line, and
+    /// `/* ... */`) may appear wherever whitespace may, which JSON doesn't
+    /// allow but JSONC (JSON with comments) does. Some of the comments are
+    /// meant to trip up parsers, e.g. `/*/ */`, `/* /* */` or `// */`.
+    ///
+    /// This is ignored by
+    /// [`json_invalid_grammar_mutator_with_config`](crate::json_invalid_grammar_mutator_with_config),
+    /// since a comment would be a second defect.
+    pub fn comments(mut self, comments: bool) -> Self {
+        self.comments = comments;
+        self
+    }
+
+    /// If `true`, the last element of an array or member of an object may be
+    /// followed by a comma (e.g. `[1, 2,]`), which JSON doesn't allow but
+    /// JSONC does.
+    ///
+    /// This is ignored by
+    /// [`json_invalid_grammar_mutator_with_config`](crate::json_invalid_grammar_mutator_with_config),
+    /// since trailing commas are one of the defects it generates.
+    pub fn trailing_commas(mut self, trailing_commas: bool) -> Self {
+        self.trailing_commas = trailing_commas;
+        self
+    }
+
+    /// The maximum number of digits before the decimal point of a number (33
+    /// by default). Together with the default
+    /// [`max_exponent_digits`](Self::max_exponent_digits) this keeps every
+    /// number within the range of an `f64`, which is the most many parsers
+    /// (e.g. `serde_json`) accept.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_integer_digits` is 0.
+    pub fn max_integer_digits(mut self, max_integer_digits: usize) -> Self {
+        assert!(
+            max_integer_digits > 0,
+            "numbers must have at least one integer digit"
+        );
+        self.max_integer_digits = max_integer_digits;
+        self
+    }
+
+    /// The maximum number of digits after the decimal point of a number (33 by
+    /// default). If this is 0, numbers have no fractional part.
+    pub fn max_fraction_digits(mut self, max_fraction_digits: usize) -> Self {
+        self.max_fraction_digits = max_fraction_digits;
+        self
+    }
+
+    /// The maximum number of digits in the exponent of a number (2 by
+    /// default, so `1e-99` and `1E+99` may be generated but `1e100` won't be).
+    /// If this is 0, numbers have no exponent.
+    pub fn max_exponent_digits(mut self, max_exponent_digits: usize) -> Self {
+        self.max_exponent_digits = max_exponent_digits;
+        self
+    }
+}
@@ -0,0 +1,214 @@
+//! Generates strings which are JSON except for one defect. Each kind of defect
+//! has its own grammar, in which the defect may appear anywhere in an
+//! otherwise valid document, and the mutators of these grammars are combined
+//! by [`json_invalid_grammar_mutator`](crate::json_invalid_grammar_mutator).
+
+use std::rc::Rc;
+
+use fuzzcheck::mutators::grammar::{
+    alternation, concatenation, grammar_based_ast_mutator, literal, recurse, recursive, regex,
+    repetition, Grammar, AST,
+};
+use fuzzcheck::mutators::map::MapMutator;
+use fuzzcheck::Mutator;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    blank, characters, digit, element, elements, exponent, fraction, member, members, string,
+    unescaped, value, ws, JsonGrammarConfig,
+};
+
+/// The defect which makes a string generated by
+/// [`json_invalid_grammar_mutator`](crate::json_invalid_grammar_mutator)
+/// invalid JSON.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum JsonDefect {
+    /// An array or object has a comma after its last element or member, e.g.
+    /// `[1, 2,]`.
+    TrailingComma,
+    /// The key of an object member isn't quoted, e.g. `{a: 1}`.
+    UnquotedKey,
+    /// A string (or the key of an object member) is surrounded by single
+    /// quotes, e.g. `'a'`.
+    SingleQuotes,
+    /// The integer part of a number has a leading zero, e.g. `012`. This isn't
+    /// generated if
+    /// [`max_integer_digits`](crate::JsonGrammarConfig::max_integer_digits)
+    /// is 1.
+    LeadingZero,
+    /// A number is `NaN` or infinite, e.g. `-Infinity`.
+    NonFiniteNumber,
+    /// The document ends inside a string, e.g. `["a`.
+    UnterminatedString,
+    /// A string contains a `\uXXXX` escape sequence encoding a surrogate which
+    /// isn't part of a pair, e.g. `"\ud83d"`.
+    LoneSurrogate,
+    /// An array is closed by a brace or an object by a bracket, e.g. `[1}`.
+    MismatchedBracket,
+}
+
+impl JsonDefect {
+    pub(crate) const ALL: [Self; 8] = [
+        Self::TrailingComma,
+        Self::UnquotedKey,
+        Self::SingleQuotes,
+        Self::LeadingZero,
+        Self::NonFiniteNumber,
+        Self::UnterminatedString,
+        Self::LoneSurrogate,
+        Self::MismatchedBracket,
+    ];
+}
+
+/// Generates documents containing `defect`, labelled with it.
+pub(crate) fn defect_mutator(
+    config: &JsonGrammarConfig,
+    defect: JsonDefect,
+) -> impl Mutator<(String, AST, JsonDefect)> {
+    MapMutator::new(
+        grammar_based_ast_mutator(document(config, defect)).with_string(),
+        move |(string, ast, label): &(String, AST, JsonDefect)| {
+            (*label == defect).then(|| (string.clone(), ast.clone()))
+        },
+        move |(string, ast)| (string.clone(), ast.clone(), defect),
+        |_, cplx| cplx,
+    )
+}
+
+pub(crate) fn document(config: &JsonGrammarConfig, defect: JsonDefect) -> Rc<Grammar> {
+    let defective = match defect {
+        JsonDefect::TrailingComma => alternation([
+            concatenation([
+                literal('['),
+                repetition(
+                    concatenation([element(config, value(config)), literal(',')]),
+                    1..=usize::MAX,
+                ),
+                ws(config),
+                literal(']'),
+            ]),
+            concatenation([
+                literal('{'),
+                repetition(
+                    concatenation([member(config, string(), value(config)), literal(',')]),
+                    1..=usize::MAX,
+                ),
+                ws(config),
+                literal('}'),
+            ]),
+        ]),
+        JsonDefect::UnquotedKey => object_with(
+            config,
+            member(config, regex("[a-zA-Z_$][a-zA-Z0-9_$]*"), value(config)),
+        ),
+        JsonDefect::SingleQuotes => alternation([
+            single_quoted(),
+            object_with(config, member(config, single_quoted(), value(config))),
+        ]),
+        // there is no room for a digit after the zero if numbers may only
+        // have one, in which case this defect isn't generated at all
+        JsonDefect::LeadingZero => concatenation([
+            alternation([blank(), literal('-')]),
+            literal('0'),
+            repetition(digit(), 1..=config.max_integer_digits - 1),
+            fraction(config),
+            exponent(config),
+        ]),
+        // without a plus sign, which would be a second defect
+        JsonDefect::NonFiniteNumber => regex("-?(NaN|Infinity)"),
+        // anything after the string would become part of it
+        JsonDefect::UnterminatedString => return concatenation([ws(config), unterminated(config)]),
+        JsonDefect::LoneSurrogate => concatenation([
+            literal('"'),
+            characters(),
+            alternation([
+                // a high surrogate which isn't followed by an escape sequence
+                concatenation([
+                    regex("\\\\u[dD][89abAB][0-9a-fA-F]{2}"),
+                    alternation([blank(), concatenation([unescaped(), characters()])]),
+                ]),
+                // a low surrogate which isn't preceded by a high surrogate,
+                // since `characters` never ends with one
+                concatenation([regex("\\\\u[dD][c-fC-F][0-9a-fA-F]{2}"), characters()]),
+            ]),
+            literal('"'),
+        ]),
+        JsonDefect::MismatchedBracket => alternation([
+            concatenation([literal('['), elements(config, value(config)), literal('}')]),
+            concatenation([literal('{'), members(config, value(config)), literal(']')]),
+        ]),
+    };
+    concatenation([ws(config), containing(config, defective), ws(config)])
+}
+
+/// A value which is either `defective`, or an array or object containing it
+/// (at any depth) among valid values.
+fn containing(config: &JsonGrammarConfig, defective: Rc<Grammar>) -> Rc<Grammar> {
+    recursive(|containing| {
+        alternation([
+            defective.clone(),
+            array_with(config, element(config, recurse(containing))),
+            object_with(config, member(config, string(), recurse(containing))),
+        ])
+    })
+}
+
+/// An array which contains `contained`, among valid elements.
+fn array_with(config: &JsonGrammarConfig, contained: Rc<Grammar>) -> Rc<Grammar> {
+    let valid = || element(config, value(config));
+    concatenation([
+        literal('['),
+        repetition(concatenation([valid(), literal(',')]), 0..=usize::MAX),
+        contained,
+        repetition(concatenation([literal(','), valid()]), 0..=usize::MAX),
+        literal(']'),
+    ])
+}
+
+/// An object which contains `contained`, among valid members.
+fn object_with(config: &JsonGrammarConfig, contained: Rc<Grammar>) -> Rc<Grammar> {
+    let valid = || member(config, string(), value(config));
+    concatenation([
+        literal('{'),
+        repetition(concatenation([valid(), literal(',')]), 0..=usize::MAX),
+        contained,
+        repetition(concatenation([literal(','), valid()]), 0..=usize::MAX),
+        literal('}'),
+    ])
+}
+
+fn single_quoted() -> Rc<Grammar> {
+    concatenation([literal('\''), regex("[^'\\\\\\x00-\\x1F]*"), literal('\'')])
+}
+
+/// A string which isn't terminated, possibly after the beginning of arrays
+/// and objects which are never closed (e.g. `[1, {"a": "b`).
+fn unterminated(config: &JsonGrammarConfig) -> Rc<Grammar> {
+    recursive(|unterminated| {
+        alternation([
+            concatenation([literal('"'), characters()]),
+            concatenation([
+                literal('['),
+                repetition(
+                    concatenation([element(config, value(config)), literal(',')]),
+                    0..=usize::MAX,
+                ),
+                ws(config),
+                recurse(unterminated),
+            ]),
+            concatenation([
+                literal('{'),
+                repetition(
+                    concatenation([member(config, string(), value(config)), literal(',')]),
+                    0..=usize::MAX,
+                ),
+                ws(config),
+                string(),
+                ws(config),
+                literal(':'),
+                ws(config),
+                recurse(unterminated),
+            ]),
+        ])
+    })
+}
@@ -0,0 +1,196 @@
+//! The grammar of [JSON5](https://spec.json5.org), for
+//! [`json5_grammar_mutator`](crate::json5_grammar_mutator). It is built from
+//! the same pieces as the JSON grammar, with JSON5's extensions to each of
+//! them.
+
+use std::rc::Rc;
+
+use fuzzcheck::mutators::grammar::{
+    alternation, concatenation, literal, recurse, recursive, regex, repetition, Grammar,
+};
+
+use crate::{blank, comment, digit, JsonGrammarConfig};
+
+/// A whole JSON5 document: a value, which may be surrounded by whitespace and
+/// comments.
+pub(crate) fn json5(config: &JsonGrammarConfig) -> Rc<Grammar> {
+    concatenation([ws(config), value(config), ws(config)])
+}
+
+fn value(config: &JsonGrammarConfig) -> Rc<Grammar> {
+    recursive(|value| {
+        let element = || concatenation([ws(config), recurse(value), ws(config)]);
+        let member = || {
+            concatenation([
+                ws(config),
+                alternation([identifier(), string()]),
+                ws(config),
+                literal(':'),
+                element(),
+            ])
+        };
+        alternation([
+            // null
+            regex("null"),
+            // bool
+            alternation([regex("true"), regex("false")]),
+            // number
+            number(config),
+            // string
+            string(),
+            // array
+            concatenation([
+                literal('['),
+                alternation([
+                    // empty
+                    ws(config),
+                    concatenation([
+                        repetition(concatenation([element(), literal(',')]), 0..=usize::MAX),
+                        element(),
+                        trailing_comma(config),
+                    ]),
+                ]),
+                literal(']'),
+            ]),
+            // object
+            concatenation([
+                literal('{'),
+                alternation([
+                    // empty
+                    ws(config),
+                    concatenation([
+                        repetition(concatenation([member(), literal(',')]), 0..=usize::MAX),
+                        member(),
+                        trailing_comma(config),
+                    ]),
+                ]),
+                literal('}'),
+            ]),
+        ])
+    })
+}
+
+fn trailing_comma(config: &JsonGrammarConfig) -> Rc<Grammar> {
+    alternation([blank(), concatenation([literal(','), ws(config)])])
+}
+
+/// Whitespace, which includes a few more characters than in JSON (e.g. a
+/// vertical tab or a no-break space), and comments.
+fn ws(config: &JsonGrammarConfig) -> Rc<Grammar> {
+    if config.compact {
+        return blank();
+    }
+    repetition(
+        alternation([
+            regex("[ \t\n\r]"),
+            regex("[\u{B}\u{C}\u{A0}\u{1680}\u{2000}-\u{200A}\u{2028}\u{2029}\u{202F}\u{205F}\u{3000}\u{FEFF}]"),
+            comment(),
+        ]),
+        0..=4,
+    )
+}
+
+/// The key of an object member which isn't quoted.
+fn identifier() -> Rc<Grammar> {
+    alternation([
+        regex("[a-zA-Z_$][a-zA-Z0-9_$]*"),
+        // ECMAScript allows letters and digits of any script
+        regex("[\\p{L}\\p{Nl}_$][\\p{L}\\p{Nl}\\p{Mn}\\p{Mc}\\p{Nd}\\p{Pc}_$]*"),
+    ])
+}
+
+/// A number, which (unlike in JSON) may start with a plus sign, be
+/// hexadecimal, have a decimal point without any digits before or after it,
+/// or be `Infinity` or `NaN`. The number of digits is limited as it is for
+/// JSON numbers.
+fn number(config: &JsonGrammarConfig) -> Rc<Grammar> {
+    let digits = |min: usize, max: usize| repetition(digit(), min..=max);
+    let integer = alternation([
+        literal('0'),
+        concatenation([regex("[1-9]"), digits(0, config.max_integer_digits - 1)]),
+    ]);
+    let mut decimals = vec![concatenation([integer.clone(), exponent(config)])];
+    if config.max_fraction_digits > 0 {
+        decimals.extend([
+            // `5.`, `5.25`
+            concatenation([
+                integer,
+                literal('.'),
+                digits(0, config.max_fraction_digits),
+                exponent(config),
+            ]),
+            // `.25`
+            concatenation([
+                literal('.'),
+                digits(1, config.max_fraction_digits),
+                exponent(config),
+            ]),
+        ]);
+    }
+    concatenation([
+        alternation([blank(), literal('+'), literal('-')]),
+        alternation([
+            alternation(decimals),
+            concatenation([
+                regex("0[xX]"),
+                repetition(regex("[0-9a-fA-F]"), 1..=config.max_integer_digits),
+            ]),
+            regex("Infinity"),
+            regex("NaN"),
+        ]),
+    ])
+}
+
+fn exponent(config: &JsonGrammarConfig) -> Rc<Grammar> {
+    if config.max_exponent_digits == 0 {
+        return blank();
+    }
+    alternation([
+        blank(),
+        concatenation([
+            regex("[eE][+-]?"),
+            repetition(digit(), 1..=config.max_exponent_digits),
+        ]),
+    ])
+}
+
+/// A string surrounded by double or single quotes, which may contain JSON5's
+/// additional escape sequences (e.g. `\v`, `\x41` or `\'`) and escaped line
+/// terminators, which continue the string on the next line.
+fn string() -> Rc<Grammar> {
+    alternation([
+        concatenation([
+            literal('"'),
+            characters(regex("[^\"\\\\\n\r]")),
+            literal('"'),
+        ]),
+        concatenation([
+            literal('\''),
+            characters(regex("[^'\\\\\n\r]")),
+            literal('\''),
+        ]),
+    ])
+}
+
+/// The contents of a [`string`], in which `unescaped` characters may appear
+/// without being escaped.
+fn characters(unescaped: Rc<Grammar>) -> Rc<Grammar> {
+    repetition(
+        alternation([
+            // most characters are plain ASCII, to keep strings readable
+            regex("[ !#-&(-\\[\\]-~]"),
+            unescaped,
+            regex("\\\\['\"\\\\/bfnrtv]"),
+            // a character which doesn't need to be escaped, but may be
+            regex("\\\\[^'\"\\\\bfnrtvxu0-9\n\r\u{2028}\u{2029}]"),
+            // `\0` may not be followed by a digit
+            regex("\\\\0[^0-9'\"\\\\\n\r]"),
+            regex("\\\\x[0-9a-fA-F]{2}"),
+            regex("\\\\u([0-9a-cA-CeEfF][0-9a-fA-F]{3}|[dD][0-7][0-9a-fA-F]{2})"),
+            regex("\\\\u[dD][89abAB][0-9a-fA-F]{2}\\\\u[dD][c-fC-F][0-9a-fA-F]{2}"),
+            // a line continuation
+            regex("\\\\(\n|\r\n|\r|\u{2028}|\u{2029})"),
+        ]),
+        0..=usize::MAX,
+    )
+}
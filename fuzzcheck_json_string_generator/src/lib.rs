@@ -1,13 +1,23 @@
 use std::rc::Rc;
 
 use fuzzcheck::{
-    mutators::grammar::{
-        alternation, concatenation, grammar_based_ast_mutator, literal, recurse, recursive, regex,
-        repetition, Grammar, AST,
+    mutators::{
+        alternation::AlternationMutator,
+        grammar::{
+            alternation, concatenation, grammar_based_ast_mutator, literal, recurse, recursive,
+            regex, repetition, Grammar, AST,
+        },
     },
     Mutator,
 };
 
+mod config;
+mod invalid;
+mod json5;
+
+pub use config::JsonGrammarConfig;
+pub use invalid::JsonDefect;
+
 /// Generates valid JSON strings which can be used to test programs which
 /// operate on JSON data.
 ///
@@ -15,99 +25,330 @@ use fuzzcheck::{
 /// [`fuzzcheck::fuzz_test`] (or use it to build more complex mutators on top of
 /// this one).
 ///
-/// This generator is conservative; it may not generate every valid JSON string
-/// (yet - patches to improve it are welcome) but every string it generates
-/// should be valid JSON (and I've fuzzed it against serde_json to check).
+/// This generator covers the whole of the RFC 8259 grammar, except that the
+/// length of numbers and of the whitespace between tokens is limited (see
+/// [`JsonGrammarConfig`]). Every string it generates should be valid JSON (and
+/// I've fuzzed it against serde_json to check).
 pub fn json_grammar_mutator() -> impl Mutator<(String, AST)> {
-    let grammar = recursive(|json| {
+    json_grammar_mutator_with_config(JsonGrammarConfig::default())
+}
+
+/// Like [`json_grammar_mutator`], but the strings are generated according to
+/// `config` (e.g. without whitespace between tokens, see
+/// [`JsonGrammarConfig::compact`]).
+pub fn json_grammar_mutator_with_config(config: JsonGrammarConfig) -> impl Mutator<(String, AST)> {
+    grammar_based_ast_mutator(json(&config)).with_string()
+}
+
+/// Generates strings which are almost JSON: each one has a single defect (e.g.
+/// a trailing comma, an unquoted key or a `NaN`), which is returned along with
+/// it, somewhere in an otherwise valid document. This can be used to test that
+/// a parser rejects invalid input, and how it reports the error.
+///
+/// ```
+/// use fuzzcheck::Mutator;
+/// use fuzzcheck_json_string_generator::json_invalid_grammar_mutator;
+///
+/// let mutator = json_invalid_grammar_mutator();
+/// let ((string, _, defect), _) = mutator.random_arbitrary(256.0);
+/// assert!(
+///     serde_json::from_str::<serde_json::Value>(&string).is_err(),
+///     "{string:?} has a {defect:?} but was accepted"
+/// );
+/// ```
+pub fn json_invalid_grammar_mutator() -> impl Mutator<(String, AST, JsonDefect)> {
+    json_invalid_grammar_mutator_with_config(JsonGrammarConfig::default())
+}
+
+/// Like [`json_invalid_grammar_mutator`], but the valid parts of the strings
+/// are generated according to `config`.
+pub fn json_invalid_grammar_mutator_with_config(
+    config: JsonGrammarConfig,
+) -> impl Mutator<(String, AST, JsonDefect)> {
+    let config = JsonGrammarConfig {
+        comments: false,
+        trailing_commas: false,
+        ..config
+    };
+    AlternationMutator::new(
+        JsonDefect::ALL
+            .iter()
+            .filter(|defect| **defect != JsonDefect::LeadingZero || config.max_integer_digits > 1)
+            .map(|defect| invalid::defect_mutator(&config, *defect))
+            .collect(),
+        0.0,
+    )
+}
+
+/// Generates valid [JSON5](https://spec.json5.org) strings, using all of its
+/// extensions to JSON: comments, unquoted keys, single-quoted strings,
+/// trailing commas, hexadecimal numbers, numbers with a leading or trailing
+/// decimal point, `Infinity` and `NaN`, and strings spanning several lines.
+///
+/// [`JsonGrammarConfig`] applies to JSON5 as well: if it is
+/// [`compact`](JsonGrammarConfig::compact) then no whitespace or comments are
+/// generated, and it limits the number of digits in numbers (including
+/// hexadecimal ones). Its [`comments`](JsonGrammarConfig::comments) and
+/// [`trailing_commas`](JsonGrammarConfig::trailing_commas) are ignored, since
+/// JSON5 always allows both.
+pub fn json5_grammar_mutator() -> impl Mutator<(String, AST)> {
+    json5_grammar_mutator_with_config(JsonGrammarConfig::default())
+}
+
+/// Like [`json5_grammar_mutator`], but the strings are generated according to
+/// `config`.
+pub fn json5_grammar_mutator_with_config(config: JsonGrammarConfig) -> impl Mutator<(String, AST)> {
+    grammar_based_ast_mutator(json5::json5(&config)).with_string()
+}
+
+/// Generates [NDJSON](https://github.com/ndjson/ndjson-spec) (or JSON Lines)
+/// streams: JSON documents, each on its own line. The lines may end with
+/// `\n` or `\r\n`, be separated by blank lines, and the last one may not
+/// end with a line break at all.
+///
+/// The documents are generated by the same grammar as those of
+/// [`json_grammar_mutator`], except that the whitespace inside them never
+/// contains a line break.
+///
+/// ```
+/// use fuzzcheck::Mutator;
+/// use fuzzcheck_json_string_generator::ndjson_grammar_mutator;
+///
+/// let mutator = ndjson_grammar_mutator();
+/// let ((stream, _), _) = mutator.random_arbitrary(256.0);
+/// for line in stream.lines().filter(|line| !line.trim().is_empty()) {
+///     serde_json::from_str::<serde_json::Value>(line).unwrap();
+/// }
+/// ```
+pub fn ndjson_grammar_mutator() -> impl Mutator<(String, AST)> {
+    ndjson_grammar_mutator_with_config(JsonGrammarConfig::default())
+}
+
+/// Like [`ndjson_grammar_mutator`], but the documents are generated according
+/// to `config`. Its [`comments`](JsonGrammarConfig::comments) and
+/// [`trailing_commas`](JsonGrammarConfig::trailing_commas) are ignored, since
+/// NDJSON doesn't allow them.
+pub fn ndjson_grammar_mutator_with_config(
+    config: JsonGrammarConfig,
+) -> impl Mutator<(String, AST)> {
+    let config = JsonGrammarConfig {
+        comments: false,
+        trailing_commas: false,
+        single_line: true,
+        ..config
+    };
+    grammar_based_ast_mutator(ndjson(&config)).with_string()
+}
+
+/// A whole JSON document: a value, which may be surrounded by whitespace.
+fn json(config: &JsonGrammarConfig) -> Rc<Grammar> {
+    concatenation([ws(config), value(config), ws(config)])
+}
+
+/// Documents separated by line breaks, and possibly blank lines.
+fn ndjson(config: &JsonGrammarConfig) -> Rc<Grammar> {
+    let line_break = || alternation([literal('\n'), regex("\r\n")]);
+    let separator = alternation([
+        line_break(),
+        // blank lines, which may contain whitespace
+        concatenation([
+            line_break(),
+            repetition(concatenation([ws(config), line_break()]), 1..=2),
+        ]),
+    ]);
+    concatenation([
+        repetition(concatenation([json(config), separator]), 0..=usize::MAX),
+        // the last line doesn't need to end with a line break
+        alternation([blank(), json(config)]),
+    ])
+}
+
+fn value(config: &JsonGrammarConfig) -> Rc<Grammar> {
+    recursive(|value| {
         alternation([
             // null
             regex("null"),
             // bool
             alternation([regex("true"), regex("false")]),
             // number
-            number(),
+            number(config),
             // string
-            concatenation([quote(), valid_possibly_empty_string(), quote()]),
+            string(),
             // array
-            concatenation([
-                literal('['),
-                repetition(concatenation([recurse(json), literal(',')]), 0..=usize::MAX),
-                // can't have a trailing comma here
-                recurse(json),
-                literal(']'),
-            ]),
+            concatenation([literal('['), elements(config, recurse(value)), literal(']')]),
             // object
-            concatenation([
-                literal('{'),
-                repetition(
-                    concatenation([
-                        quote(),
-                        valid_possibly_empty_string(),
-                        quote(),
-                        literal(':'),
-                        recurse(json),
-                        literal(','),
-                    ]),
-                    0..=usize::MAX,
-                ),
-                concatenation([
-                    quote(),
-                    valid_possibly_empty_string(),
-                    quote(),
-                    literal(':'),
-                    recurse(json),
-                ]),
-                literal('}'),
-            ]),
+            concatenation([literal('{'), members(config, recurse(value)), literal('}')]),
         ])
-    });
-    grammar_based_ast_mutator(grammar).with_string()
+    })
+}
+
+/// The contents of an array whose elements are `value`s: nothing but
+/// whitespace, or elements separated by commas.
+fn elements(config: &JsonGrammarConfig, value: Rc<Grammar>) -> Rc<Grammar> {
+    alternation([
+        // empty
+        ws(config),
+        concatenation([
+            repetition(
+                concatenation([element(config, value.clone()), literal(',')]),
+                0..=usize::MAX,
+            ),
+            // can't have a trailing comma here, unless they're allowed
+            element(config, value),
+            trailing_comma(config),
+        ]),
+    ])
+}
+
+/// The contents of an object whose members have string keys and `value`s:
+/// nothing but whitespace, or members separated by commas.
+fn members(config: &JsonGrammarConfig, value: Rc<Grammar>) -> Rc<Grammar> {
+    alternation([
+        // empty
+        ws(config),
+        concatenation([
+            repetition(
+                concatenation([member(config, string(), value.clone()), literal(',')]),
+                0..=usize::MAX,
+            ),
+            member(config, string(), value),
+            trailing_comma(config),
+        ]),
+    ])
 }
 
-fn quote() -> Rc<Grammar> {
-    literal('"')
+/// A value inside an array or object, along with the whitespace which may
+/// surround it.
+fn element(config: &JsonGrammarConfig, value: Rc<Grammar>) -> Rc<Grammar> {
+    concatenation([ws(config), value, ws(config)])
+}
+
+fn member(config: &JsonGrammarConfig, key: Rc<Grammar>, value: Rc<Grammar>) -> Rc<Grammar> {
+    concatenation([
+        ws(config),
+        key,
+        ws(config),
+        literal(':'),
+        element(config, value),
+    ])
 }
 
-fn number() -> Rc<Grammar> {
-    concatenation([digits(), fraction(), exponent()])
+/// A comma after the last element or member of a non-empty array or object,
+/// if the configuration allows it.
+fn trailing_comma(config: &JsonGrammarConfig) -> Rc<Grammar> {
+    if config.trailing_commas {
+        alternation([blank(), concatenation([literal(','), ws(config)])])
+    } else {
+        blank()
+    }
+}
+
+/// Whitespace (and comments, if the configuration allows them) which may
+/// appear between two tokens, or nothing if the output should be compact.
+fn ws(config: &JsonGrammarConfig) -> Rc<Grammar> {
+    let mut ws = Vec::new();
+    if !config.compact {
+        // a line break would end an NDJSON document
+        ws.push(match config.single_line {
+            true => regex("[ \t]"),
+            false => regex("[ \t\n\r]"),
+        });
+    }
+    if config.comments {
+        ws.push(jsonc_comment());
+    }
+    if ws.is_empty() {
+        return blank();
+    }
+    repetition(alternation(ws), 0..=4)
+}
+
+/// A comment, either running to the end of the line (which it always
+/// includes, or everything after the comment would be part of it) or
+/// delimited by `/*` and `*/`.
+fn comment() -> Rc<Grammar> {
+    alternation([
+        regex("//[^\n\r\u{2028}\u{2029}]*[\n\r\u{2028}\u{2029}]"),
+        regex("/\\*([^*]|\\*+[^*/])*\\*+/"),
+    ])
+}
+
+/// A [`comment`] of JSONC, in which (as in JSON's whitespace) only a line
+/// feed or carriage return ends a line.
+fn jsonc_comment() -> Rc<Grammar> {
+    let text = |text: &str| concatenation(text.chars().map(literal));
+    alternation([
+        regex("//[^\n\r]*[\n\r]"),
+        regex("/\\*([^*]|\\*+[^*/])*\\*+/"),
+        // comments which a parser might end too early or too late
+        alternation([
+            text("/**/"),
+            text("/***/"),
+            text("/*/ */"),
+            text("/*/**/"),
+            text("/* **/"),
+            text("/* * / */"),
+            text("/* /* */"),
+            text("/* // */"),
+            text("// */\n"),
+            text("// /*\n"),
+            text("//\\\n"),
+        ]),
+    ])
+}
+
+/// A number, as defined by RFC 8259: an optional minus sign, then `0` or an
+/// integer without leading zeros, then an optional fraction and exponent. The
+/// number of digits in each part is limited by `config`, because many parsers
+/// (e.g. Rust's serde_json) refuse to parse numbers which are too large for the
+/// language provided types.
+fn number(config: &JsonGrammarConfig) -> Rc<Grammar> {
+    concatenation([
+        alternation([blank(), literal('-')]),
+        integer(config),
+        fraction(config),
+        exponent(config),
+    ])
 }
 
-/// We only generate a subset of numbers because if the numbers are too big then
-/// serde_json will refuse to deserialize them.
-fn digits() -> Rc<Grammar> {
-    concatenation([regex("[1-9]"), repetition(digit(), 0..=32)])
+fn integer(config: &JsonGrammarConfig) -> Rc<Grammar> {
+    alternation([
+        literal('0'),
+        concatenation([
+            regex("[1-9]"),
+            repetition(digit(), 0..=config.max_integer_digits - 1),
+        ]),
+    ])
 }
 
 fn digit() -> Rc<Grammar> {
     regex("[0-9]")
 }
 
-fn fraction() -> Rc<Grammar> {
+fn fraction(config: &JsonGrammarConfig) -> Rc<Grammar> {
+    if config.max_fraction_digits == 0 {
+        return blank();
+    }
     alternation([
         // i.e. nothing
         blank(),
-        concatenation([literal('.'), digits()]),
+        concatenation([
+            literal('.'),
+            repetition(digit(), 1..=config.max_fraction_digits),
+        ]),
     ])
 }
 
-/// We only generate a few numbers for the exponent, because many parsers (e.g.
-/// Rust's serde_json) refuse to parse integers larger than than the language
-/// provided types.
-fn exponent() -> Rc<Grammar> {
+fn exponent(config: &JsonGrammarConfig) -> Rc<Grammar> {
+    if config.max_exponent_digits == 0 {
+        return blank();
+    }
     alternation([
         blank(),
         concatenation([
-            literal('E'),
+            alternation([literal('e'), literal('E')]),
             sign(),
-            regex("[1-9]"),
-            repetition(regex("[0-9]"), 0..=1),
-        ]),
-        concatenation([
-            literal('e'),
-            sign(),
-            regex("[1-9]"),
-            repetition(regex("[0-9]"), 0..=1),
+            repetition(digit(), 1..=config.max_exponent_digits),
         ]),
     ])
 }
@@ -116,14 +357,173 @@ fn sign() -> Rc<Grammar> {
     alternation([blank(), literal('+'), literal('-')])
 }
 
-fn valid_possibly_empty_string() -> Rc<Grammar> {
-    regex("[a-zA-Z0-9_]*")
+/// A string, as defined by RFC 8259: any Unicode characters other than
+/// quotation marks, reverse solidi and control characters, and the escape
+/// sequences `\"`, `\\`, `\/`, `\b`, `\f`, `\n`, `\r`, `\t` and `\uXXXX`.
+/// The latter never encode a lone surrogate: surrogates are only escaped in
+/// pairs, such as `\ud83d\ude00`.
+fn string() -> Rc<Grammar> {
+    concatenation([literal('"'), characters(), literal('"')])
+}
+
+/// The contents of a [`string`], without the quotation marks.
+fn characters() -> Rc<Grammar> {
+    repetition(
+        alternation([
+            // most characters are plain ASCII, to keep strings readable
+            regex("[ !#-\\[\\]-~]"),
+            unescaped(),
+            regex("\\\\[\"\\\\/bfnrt]"),
+            // a code point in the Basic Multilingual Plane, other than a
+            // surrogate
+            regex("\\\\u([0-9a-cA-CeEfF][0-9a-fA-F]{3}|[dD][0-7][0-9a-fA-F]{2})"),
+            // a high surrogate followed by a low surrogate
+            regex("\\\\u[dD][89abAB][0-9a-fA-F]{2}\\\\u[dD][c-fC-F][0-9a-fA-F]{2}"),
+        ]),
+        0..=usize::MAX,
+    )
+}
+
+/// A character which may appear in a string without being escaped.
+fn unescaped() -> Rc<Grammar> {
+    regex("[^\"\\\\\\x00-\\x1F]")
 }
 
 fn blank() -> Rc<Grammar> {
     repetition(literal(' '), 0..=0)
 }
 
+/// Generates values with `mutator` and mutates each of them a few times,
+/// undoing every mutation after it is checked as the fuzzer would, and calls
+/// `check` on every value.
+#[cfg(test)]
+fn mutate_and_check<T, M>(mutator: &M, mut check: impl FnMut(&T))
+where
+    T: Clone + 'static,
+    M: Mutator<T>,
+{
+    use fuzzcheck::subvalue_provider::EmptySubValueProvider;
+
+    for _ in 0..1000 {
+        let (mut value, _) = mutator.random_arbitrary(256.0);
+        check(&value);
+        let mut cache = mutator.validate_value(&value).unwrap();
+        let mut step = mutator.default_mutation_step(&value, &cache);
+        for _ in 0..10 {
+            let Some((token, _)) = mutator.ordered_mutate(
+                &mut value,
+                &mut cache,
+                &mut step,
+                &EmptySubValueProvider,
+                256.0,
+            ) else {
+                break;
+            };
+            check(&value);
+            mutator.unmutate(&mut value, &mut cache, token);
+        }
+    }
+}
+
+/// Whether `grammar` can generate `string`, which is checked by building the
+/// syntax tree of `string` and letting the mutator of `grammar` validate it.
+#[cfg(test)]
+fn accepts(grammar: Rc<Grammar>, string: &str) -> bool {
+    /// Every way `grammar` matches a prefix of `chars[start..]`, as the
+    /// position the match ends at and its syntax tree (only the first tree is
+    /// kept for each position).
+    fn parse(grammar: &Grammar, chars: &[char], start: usize) -> Vec<(usize, AST)> {
+        let first_per_end = |parses: Vec<(usize, AST)>| {
+            let mut kept: Vec<(usize, AST)> = Vec::new();
+            for (end, ast) in parses {
+                if kept.iter().all(|(kept_end, _)| *kept_end != end) {
+                    kept.push((end, ast));
+                }
+            }
+            kept
+        };
+        match grammar {
+            Grammar::Literal(ranges) => chars
+                .get(start)
+                .filter(|c| ranges.iter().any(|range| range.contains(c)))
+                .map(|c| (start + 1, AST::Token(*c)))
+                .into_iter()
+                .collect(),
+            Grammar::Alternation(grammars) => first_per_end(
+                grammars
+                    .iter()
+                    .flat_map(|grammar| parse(grammar, chars, start))
+                    .collect(),
+            ),
+            Grammar::Concatenation(grammars) => {
+                let mut partial = vec![(start, Vec::new())];
+                for grammar in grammars {
+                    let mut next: Vec<(usize, Vec<AST>)> = Vec::new();
+                    for (pos, asts) in partial {
+                        for (end, ast) in parse(grammar, chars, pos) {
+                            if next.iter().all(|(next_end, _)| *next_end != end) {
+                                next.push((end, [asts.clone(), vec![ast]].concat()));
+                            }
+                        }
+                    }
+                    partial = next;
+                }
+                partial
+                    .into_iter()
+                    .map(|(end, asts)| (end, AST::Sequence(asts)))
+                    .collect()
+            }
+            Grammar::Repetition(grammar, range) => {
+                let mut parses = Vec::new();
+                let mut partial = vec![(start, Vec::new())];
+                for count in 0.. {
+                    if range.contains(&count) {
+                        parses.extend(
+                            partial
+                                .iter()
+                                .map(|(end, asts)| (*end, AST::Sequence(asts.clone()))),
+                        );
+                    }
+                    if partial.is_empty() || count + 1 >= range.end {
+                        break;
+                    }
+                    let mut next: Vec<(usize, Vec<AST>)> = Vec::new();
+                    for (pos, asts) in partial {
+                        // repeating an empty match would never end
+                        for (end, ast) in parse(grammar, chars, pos) {
+                            if end > pos && next.iter().all(|(next_end, _)| *next_end != end) {
+                                next.push((end, [asts.clone(), vec![ast]].concat()));
+                            }
+                        }
+                    }
+                    partial = next;
+                }
+                first_per_end(parses)
+            }
+            Grammar::Recurse(grammar) => parse(&grammar.upgrade().unwrap(), chars, start)
+                .into_iter()
+                .map(|(end, ast)| (end, AST::Sequence(vec![ast])))
+                .collect(),
+            Grammar::Recursive(grammar) => parse(grammar, chars, start),
+        }
+    }
+
+    let chars = string.chars().collect::<Vec<_>>();
+    let Some((_, ast)) = parse(&grammar, &chars, 0)
+        .into_iter()
+        .find(|(end, _)| *end == chars.len())
+    else {
+        return false;
+    };
+    assert!(
+        grammar_based_ast_mutator(grammar)
+            .validate_value(&ast)
+            .is_some(),
+        "the syntax tree of {string:?} was rejected"
+    );
+    true
+}
+
 #[cfg(test)]
 #[test]
 fn test_mutator() {
@@ -143,3 +543,679 @@ fn test_mutator() {
 
     assert!(!result.found_test_failure)
 }
+
+#[cfg(test)]
+#[test]
+fn check_whitespace() {
+    use serde_json::Value;
+
+    /// Whether `string` contains whitespace outside of its JSON strings.
+    fn has_whitespace(string: &str) -> bool {
+        let mut in_string = false;
+        let mut escaped = false;
+        for c in string.chars() {
+            match (in_string, escaped, c) {
+                (true, true, _) => escaped = false,
+                (true, false, '\\') => escaped = true,
+                (_, false, '"') => in_string = !in_string,
+                (false, _, ' ' | '\t' | '\n' | '\r') => return true,
+                _ => {}
+            }
+        }
+        false
+    }
+
+    let compact = JsonGrammarConfig::new().compact(true);
+    mutate_and_check(
+        &json_grammar_mutator_with_config(compact.clone()),
+        |(string, _)| {
+            serde_json::from_str::<Value>(string).unwrap();
+            assert!(!has_whitespace(string), "{string:?} isn't compact");
+        },
+    );
+
+    for string in [
+        " 1",
+        "1\n",
+        "[ 1]",
+        "[1 ,2]",
+        "{\"a\" :1}",
+        "{\"a\":\t1}",
+        "[\r\n1]",
+    ] {
+        assert!(
+            accepts(json(&JsonGrammarConfig::default()), string),
+            "{string:?}"
+        );
+        assert!(!accepts(json(&compact), string), "{string:?}");
+    }
+    assert!(accepts(json(&compact), "[1,{\"a\":\" \"}]"));
+}
+
+#[cfg(test)]
+#[test]
+fn check_empty_containers() {
+    for string in ["[]", "{}", "[ ]", "{\n}", "[[],{}]", "{\"a\":[]}"] {
+        assert!(
+            accepts(json(&JsonGrammarConfig::default()), string),
+            "{string:?}"
+        );
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn check_numbers() {
+    use serde_json::Value;
+
+    let config = JsonGrammarConfig::default();
+    mutate_and_check(
+        &grammar_based_ast_mutator(number(&config)).with_string(),
+        |(literal, _)| {
+            let value: Value = serde_json::from_str(literal).unwrap();
+            assert!(value.is_number(), "{literal:?} isn't a number");
+            let integer = literal.trim_start_matches('-');
+            assert!(
+                integer == "0"
+                    || !integer.starts_with('0')
+                    || integer[1..].starts_with(['.', 'e', 'E']),
+                "{literal:?} has a leading zero"
+            );
+        },
+    );
+    let longest = "9".repeat(33);
+    for literal in [
+        "0",
+        "-0",
+        "7",
+        "-10",
+        "0.5",
+        "-0.05",
+        "1e5",
+        "1E+05",
+        "2e-3",
+        "-12.34e-56",
+        &longest,
+    ] {
+        assert!(accepts(number(&config), literal), "{literal:?}");
+    }
+    let too_long = "9".repeat(34);
+    for literal in [
+        "01", "-01", "00", "+1", "1.", ".5", "1e", "1e+", "-", "1e100", "NaN", &too_long,
+    ] {
+        assert!(!accepts(number(&config), literal), "{literal:?}");
+    }
+
+    let config = JsonGrammarConfig::new()
+        .max_integer_digits(3)
+        .max_fraction_digits(0)
+        .max_exponent_digits(0);
+    mutate_and_check(
+        &grammar_based_ast_mutator(number(&config)).with_string(),
+        |(literal, _)| {
+            // `-0` is read as a float
+            let value: f64 = serde_json::from_str(literal).unwrap();
+            assert!(
+                value.fract() == 0.0 && value.abs() < 1000.0,
+                "{literal:?} isn't a small integer"
+            );
+        },
+    );
+    for literal in ["0", "-7", "999"] {
+        assert!(accepts(number(&config), literal), "{literal:?}");
+    }
+    for literal in ["1000", "1.5", "1e2"] {
+        assert!(!accepts(number(&config), literal), "{literal:?}");
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn check_strings() {
+    use serde_json::Value;
+
+    mutate_and_check(
+        &grammar_based_ast_mutator(string()).with_string(),
+        |(literal, _)| {
+            let value: Value = serde_json::from_str(literal)
+                .unwrap_or_else(|error| panic!("{literal:?} was rejected: {error}"));
+            assert!(value.is_string(), "{literal:?} isn't a string");
+        },
+    );
+    for literal in [
+        "\"\"",
+        "\"a b\"",
+        "\"\\\"\\\\\\/\\b\\f\\n\\r\\t\"",
+        "\"\\u00e9\\uFFFF\"",
+        "\"\\ud83d\\ude00\"",
+        "\"é\u{7F}\u{2028}😀\"",
+    ] {
+        assert!(accepts(string(), literal), "{literal:?}");
+    }
+    for literal in [
+        "\"a",
+        "\"\"\"",
+        "\"\\a\"",
+        "\"\\x41\"",
+        "\"\\u12\"",
+        "\"\\ud83d\"",
+        "\"\\ude00\\ud83d\"",
+        "\"\t\"",
+        "\"\u{0}\"",
+    ] {
+        assert!(!accepts(string(), literal), "{literal:?}");
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn check_mutations() {
+    use serde_json::Value;
+
+    mutate_and_check(&json_grammar_mutator(), |(string, _)| {
+        serde_json::from_str::<Value>(string)
+            .unwrap_or_else(|error| panic!("{string:?} was rejected: {error}"));
+    });
+}
+
+#[cfg(test)]
+#[test]
+fn check_invalid() {
+    use serde_json::Value;
+
+    mutate_and_check(
+        &json_invalid_grammar_mutator(),
+        |(string, _, defect): &(String, AST, JsonDefect)| {
+            assert!(
+                serde_json::from_str::<Value>(string).is_err(),
+                "{string:?} has a {defect:?} but was accepted"
+            );
+        },
+    );
+
+    let config = JsonGrammarConfig::default();
+    for defect in JsonDefect::ALL {
+        let examples: &[&str] = match defect {
+            JsonDefect::TrailingComma => &["[1,]", "{\"a\": 1 ,}", "[0, {\"a\": [[],]}]"],
+            JsonDefect::UnquotedKey => &["{a: 1}", "{\"a\": 1, $b_2: 2}"],
+            JsonDefect::SingleQuotes => &["'a'", "{'a': 1}", "[1, 'b']"],
+            JsonDefect::LeadingZero => &["012", "-00.5e+10", "[0, {\"a\": 07}]"],
+            JsonDefect::NonFiniteNumber => &["NaN", "Infinity", "-Infinity"],
+            JsonDefect::UnterminatedString => &["\"a", "[1, {\"a\": \"b"],
+            JsonDefect::LoneSurrogate => &["\"\\ud83d\"", "\"\\ude00a\"", "[\"\\ud83dx\"]"],
+            JsonDefect::MismatchedBracket => &["[1}", "{]", "[{\"a\": []]]"],
+        };
+        for example in examples {
+            assert!(
+                accepts(invalid::document(&config, defect), example),
+                "{example:?} has a {defect:?}"
+            );
+        }
+        for valid in ["1", "[0, {\"a\": \"b\"}]", "\"\\ud83d\\ude00\"", "+1", "+0"] {
+            assert!(
+                !accepts(invalid::document(&config, defect), valid),
+                "{valid:?} doesn't have a {defect:?}"
+            );
+        }
+    }
+
+    // the defects are the only problem with the numbers, which are otherwise
+    // within the limits of the configuration
+    let config = JsonGrammarConfig::new()
+        .max_integer_digits(3)
+        .max_fraction_digits(0)
+        .max_exponent_digits(0);
+    for (defect, literal, accepted) in [
+        (JsonDefect::NonFiniteNumber, "-NaN", true),
+        (JsonDefect::NonFiniteNumber, "+NaN", false),
+        (JsonDefect::NonFiniteNumber, "+Infinity", false),
+        (JsonDefect::LeadingZero, "01", true),
+        (JsonDefect::LeadingZero, "-099", true),
+        (JsonDefect::LeadingZero, "+01", false),
+        (JsonDefect::LeadingZero, "0999", false),
+        (JsonDefect::LeadingZero, "01.5", false),
+        (JsonDefect::LeadingZero, "01e5", false),
+    ] {
+        assert_eq!(
+            accepts(invalid::document(&config, defect), literal),
+            accepted,
+            "{literal:?}"
+        );
+    }
+
+    // a leading zero leaves no room for another digit
+    mutate_and_check(
+        &json_invalid_grammar_mutator_with_config(JsonGrammarConfig::new().max_integer_digits(1)),
+        |(string, _, defect): &(String, AST, JsonDefect)| {
+            assert_ne!(*defect, JsonDefect::LeadingZero, "{string:?}");
+        },
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn check_json5() {
+    use std::collections::HashSet;
+
+    /// A JSON5 parser which only checks that its input is valid, and records
+    /// which of JSON5's extensions to JSON it uses.
+    struct Parser {
+        chars: Vec<char>,
+        pos: usize,
+        features: HashSet<&'static str>,
+    }
+
+    impl Parser {
+        fn parse(string: &str) -> Result<HashSet<&'static str>, String> {
+            let mut parser = Self {
+                chars: string.chars().collect(),
+                pos: 0,
+                features: HashSet::new(),
+            };
+            parser.ws()?;
+            parser.value()?;
+            parser.ws()?;
+            match parser.peek() {
+                None => Ok(parser.features),
+                Some(_) => Err(parser.error("expected the end of the document")),
+            }
+        }
+
+        fn peek(&self) -> Option<char> {
+            self.chars.get(self.pos).copied()
+        }
+
+        fn starts_with(&self, prefix: &str) -> bool {
+            prefix
+                .chars()
+                .enumerate()
+                .all(|(i, c)| self.chars.get(self.pos + i) == Some(&c))
+        }
+
+        fn eat(&mut self, prefix: &str) -> bool {
+            let found = self.starts_with(prefix);
+            if found {
+                self.pos += prefix.chars().count();
+            }
+            found
+        }
+
+        fn expect(&mut self, prefix: &str) -> Result<(), String> {
+            match self.eat(prefix) {
+                true => Ok(()),
+                false => Err(self.error(&format!("expected {prefix:?}"))),
+            }
+        }
+
+        fn error(&self, what: &str) -> String {
+            format!("{what} at character {}", self.pos)
+        }
+
+        fn ws(&mut self) -> Result<(), String> {
+            loop {
+                if self.eat("//") {
+                    self.features.insert("comment");
+                    while !matches!(
+                        self.peek(),
+                        None | Some('\n' | '\r' | '\u{2028}' | '\u{2029}')
+                    ) {
+                        self.pos += 1;
+                    }
+                } else if self.eat("/*") {
+                    self.features.insert("comment");
+                    while !self.eat("*/") {
+                        self.peek()
+                            .ok_or_else(|| self.error("unterminated comment"))?;
+                        self.pos += 1;
+                    }
+                } else if matches!(
+                    self.peek(),
+                    Some(c) if c == '\u{FEFF}' || (c.is_whitespace() && (!c.is_control() || "\t\n\u{B}\u{C}\r".contains(c)))
+                ) {
+                    self.pos += 1;
+                } else {
+                    return Ok(());
+                }
+            }
+        }
+
+        fn value(&mut self) -> Result<(), String> {
+            match self.peek() {
+                Some('[') => self.container(']', false),
+                Some('{') => self.container('}', true),
+                Some('"' | '\'') => self.string(),
+                _ if self.eat("null") || self.eat("true") || self.eat("false") => Ok(()),
+                _ => self.number(),
+            }
+        }
+
+        fn container(&mut self, close: char, object: bool) -> Result<(), String> {
+            let close = close.to_string();
+            self.pos += 1;
+            self.ws()?;
+            while !self.eat(&close) {
+                if object {
+                    self.key()?;
+                    self.ws()?;
+                    self.expect(":")?;
+                    self.ws()?;
+                }
+                self.value()?;
+                self.ws()?;
+                if !self.eat(",") {
+                    return self.expect(&close);
+                }
+                self.ws()?;
+                if self.starts_with(&close) {
+                    self.features.insert("trailing comma");
+                }
+            }
+            Ok(())
+        }
+
+        fn key(&mut self) -> Result<(), String> {
+            match self.peek() {
+                Some('"' | '\'') => self.string(),
+                Some(c) if c.is_alphabetic() || c == '$' || c == '_' => {
+                    self.features.insert("identifier key");
+                    self.pos += 1;
+                    // combining marks and connector punctuation may also appear
+                    // after the first character
+                    while matches!(
+                        self.peek(),
+                        Some(c) if c.is_alphanumeric() || c == '$' || c == '_' || !(c.is_ascii() || c.is_whitespace())
+                    ) {
+                        self.pos += 1;
+                    }
+                    Ok(())
+                }
+                _ => Err(self.error("expected a key")),
+            }
+        }
+
+        fn string(&mut self) -> Result<(), String> {
+            let quote = self.chars[self.pos];
+            if quote == '\'' {
+                self.features.insert("single quotes");
+            }
+            self.pos += 1;
+            loop {
+                let c = self
+                    .peek()
+                    .ok_or_else(|| self.error("unterminated string"))?;
+                self.pos += 1;
+                match c {
+                    '\n' | '\r' => return Err(self.error("line terminator in string")),
+                    '\\' => match self
+                        .peek()
+                        .ok_or_else(|| self.error("unterminated string"))?
+                    {
+                        'x' => self.hex_digits(2)?,
+                        'u' => self.hex_digits(4)?,
+                        '0' if matches!(self.chars.get(self.pos + 1), Some('0'..='9')) => {
+                            return Err(self.error("digit after \\0"))
+                        }
+                        '1'..='9' => return Err(self.error("octal escape sequence")),
+                        '\n' | '\r' | '\u{2028}' | '\u{2029}' => {
+                            self.features.insert("line continuation");
+                            if self.eat("\r\n") {
+                                continue;
+                            }
+                        }
+                        _ => {}
+                    },
+                    c if c == quote => return Ok(()),
+                    _ => continue,
+                }
+                // skips the character after the reverse solidus (or the last
+                // hexadecimal digit)
+                self.pos += 1;
+            }
+        }
+
+        /// Checks that the `len` characters after the current one are
+        /// hexadecimal digits.
+        fn hex_digits(&mut self, len: usize) -> Result<(), String> {
+            for _ in 0..len {
+                self.pos += 1;
+                match self.peek() {
+                    Some(c) if c.is_ascii_hexdigit() => {}
+                    _ => return Err(self.error("expected a hexadecimal digit")),
+                }
+            }
+            Ok(())
+        }
+
+        /// Consumes digits, returning how many there were.
+        fn digits(&mut self) -> usize {
+            let start = self.pos;
+            while matches!(self.peek(), Some('0'..='9')) {
+                self.pos += 1;
+            }
+            self.pos - start
+        }
+
+        fn number(&mut self) -> Result<(), String> {
+            if self.eat("+") {
+                self.features.insert("plus sign");
+            } else {
+                self.eat("-");
+            }
+            if self.eat("Infinity") || self.eat("NaN") {
+                self.features.insert("Infinity or NaN");
+                return Ok(());
+            }
+            if self.eat("0x") || self.eat("0X") {
+                self.features.insert("hexadecimal number");
+                let start = self.pos;
+                while matches!(self.peek(), Some(c) if c.is_ascii_hexdigit()) {
+                    self.pos += 1;
+                }
+                return match self.pos > start {
+                    true => Ok(()),
+                    false => Err(self.error("expected a hexadecimal digit")),
+                };
+            }
+            let integer = self.digits();
+            if integer > 1 && self.chars[self.pos - integer] == '0' {
+                return Err(self.error("leading zero"));
+            }
+            if self.eat(".") {
+                let fraction = self.digits();
+                if integer == 0 && fraction == 0 {
+                    return Err(self.error("expected a digit"));
+                } else if integer == 0 || fraction == 0 {
+                    self.features.insert("leading or trailing decimal point");
+                }
+            } else if integer == 0 {
+                return Err(self.error("expected a value"));
+            }
+            if self.eat("e") || self.eat("E") {
+                let _ = self.eat("+") || self.eat("-");
+                if self.digits() == 0 {
+                    return Err(self.error("expected a digit"));
+                }
+            }
+            Ok(())
+        }
+    }
+
+    mutate_and_check(&json5_grammar_mutator(), |(string, _)| {
+        if let Err(error) = Parser::parse(string) {
+            panic!("{string:?} isn't JSON5: {error}");
+        }
+    });
+
+    for (feature, example) in [
+        ("comment", "// a\n1 /* b */"),
+        ("identifier key", "{a: 1, $é_2: 2}"),
+        ("single quotes", "['a', \"'\"]"),
+        ("trailing comma", "[1, {\"a\": 2,},]"),
+        ("plus sign", "+1"),
+        ("hexadecimal number", "-0x1F"),
+        ("leading or trailing decimal point", "[.5, 5.]"),
+        ("Infinity or NaN", "[-Infinity, NaN]"),
+        ("line continuation", "'a\\\nb'"),
+        ("line continuation", "\"a\\\r\nb\\\u{2028}c\""),
+    ] {
+        assert!(
+            Parser::parse(example).is_ok_and(|found| found.contains(feature)),
+            "{example:?} doesn't have a {feature}"
+        );
+        assert!(
+            accepts(json5::json5(&JsonGrammarConfig::default()), example),
+            "{example:?}"
+        );
+    }
+    for invalid in ["01", "0x", ".", "'a", "\"\\01\"", "{a b: 1}", "[,]", "/* a"] {
+        assert!(Parser::parse(invalid).is_err(), "{invalid:?}");
+        assert!(
+            !accepts(json5::json5(&JsonGrammarConfig::default()), invalid),
+            "{invalid:?}"
+        );
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn check_jsonc() {
+    use serde_json::Value;
+
+    /// Replaces the comments of `string` with spaces and removes its trailing
+    /// commas, returning the resulting JSON along with whether there were any
+    /// comments and trailing commas.
+    fn to_json(string: &str) -> (String, bool, bool) {
+        let (mut json, mut comments, mut trailing_commas) = (String::new(), false, false);
+        let mut chars = string.chars().peekable();
+        // the index in `json` of a comma which may be a trailing comma
+        let mut comma = None;
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => {
+                    json.push(c);
+                    while let Some(c) = chars.next() {
+                        json.push(c);
+                        match c {
+                            '\\' => json.extend(chars.next()),
+                            '"' => break,
+                            _ => {}
+                        }
+                    }
+                }
+                '/' if chars.peek() == Some(&'/') => {
+                    comments = true;
+                    while !matches!(chars.next(), None | Some('\n' | '\r')) {}
+                    json.push(' ');
+                    continue;
+                }
+                '/' if chars.peek() == Some(&'*') => {
+                    comments = true;
+                    chars.next();
+                    let mut last = None;
+                    for c in chars.by_ref() {
+                        if (last, c) == (Some('*'), '/') {
+                            break;
+                        }
+                        last = Some(c);
+                    }
+                    json.push(' ');
+                    continue;
+                }
+                ',' => {
+                    comma = Some(json.len());
+                    json.push(c);
+                    continue;
+                }
+                ']' | '}' => {
+                    if let Some(idx) = comma {
+                        trailing_commas = true;
+                        json.replace_range(idx..idx + 1, " ");
+                    }
+                    json.push(c);
+                }
+                ' ' | '\t' | '\n' | '\r' => {
+                    json.push(c);
+                    continue;
+                }
+                _ => json.push(c),
+            }
+            comma = None;
+        }
+        (json, comments, trailing_commas)
+    }
+
+    let jsonc = JsonGrammarConfig::new()
+        .comments(true)
+        .trailing_commas(true);
+    mutate_and_check(
+        &json_grammar_mutator_with_config(jsonc.clone()),
+        |(string, _)| {
+            let (json, _, _) = to_json(string);
+            serde_json::from_str::<Value>(&json)
+                .unwrap_or_else(|error| panic!("{string:?} isn't JSONC ({json:?}: {error})"));
+        },
+    );
+
+    for (example, has_comments, has_trailing_commas) in [
+        ("/* a */ 1", true, false),
+        ("1 // a\n", true, false),
+        ("[1 /**/, 2]", true, false),
+        ("{\"a\": /* // */ 1}", true, false),
+        ("[1,]", false, true),
+        ("{\"a\": [1, 2 ,] , }", false, true),
+        ("[1, // a\n]", true, true),
+        ("// \u{2028} */\n1", true, false),
+    ] {
+        assert!(accepts(json(&jsonc), example), "{example:?}");
+        assert!(
+            !accepts(json(&JsonGrammarConfig::default()), example),
+            "{example:?}"
+        );
+        let (_, comments, trailing_commas) = to_json(example);
+        assert_eq!(
+            (comments, trailing_commas),
+            (has_comments, has_trailing_commas)
+        );
+    }
+    // U+2028 ends a line comment in JSON5, but not in JSONC
+    assert!(!accepts(json(&jsonc), "// a\u{2028}1"));
+    assert!(accepts(
+        json5::json5(&JsonGrammarConfig::default()),
+        "// a\u{2028}1"
+    ));
+}
+
+#[cfg(test)]
+#[test]
+fn check_ndjson() {
+    use serde_json::Value;
+
+    mutate_and_check(&ndjson_grammar_mutator(), |(stream, _)| {
+        for line in stream.split('\n') {
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            if !line.trim().is_empty() {
+                serde_json::from_str::<Value>(line)
+                    .unwrap_or_else(|error| panic!("{line:?} in {stream:?}: {error}"));
+            }
+        }
+    });
+
+    let config = JsonGrammarConfig {
+        single_line: true,
+        ..JsonGrammarConfig::default()
+    };
+    for stream in [
+        "",
+        "1",
+        "1\n",
+        "1\r\n2",
+        "{\"a\": [1, 2]}\r\n[]\n",
+        "1\n\n2\n",
+        "1\n \t\r\n2",
+    ] {
+        assert!(accepts(ndjson(&config), stream), "{stream:?}");
+    }
+    for stream in ["1 2", "[1,\n2]", "\n1", "1\r2"] {
+        assert!(!accepts(ndjson(&config), stream), "{stream:?}");
+    }
+}
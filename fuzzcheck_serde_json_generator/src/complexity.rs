@@ -0,0 +1,170 @@
+//! The complexity of JSON values, which fuzzcheck uses to prefer small inputs
+//! (see [`ComplexityModel`]).
+
+use std::fmt::{self, Write as _};
+use std::io;
+
+use serde_json::Value;
+
+use crate::{map_internal_number_to_serde, InternalJsonNumber, InternalJsonValue};
+
+/// How the complexity of a value is measured. Fuzzcheck keeps the inputs of its
+/// corpus below a maximum complexity, and prefers the simplest input among
+/// those which reach the same code.
+///
+/// ```
+/// use fuzzcheck_serde_json_generator::{ComplexityModel, JsonValueMutatorConfig};
+///
+/// let config = JsonValueMutatorConfig::new().complexity_model(ComplexityModel::NodeCount);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ComplexityModel {
+    /// The number of bytes in the value serialized by
+    /// [`serde_json::to_string`], including quotes, escape sequences,
+    /// separators and every digit of the numbers.
+    #[default]
+    SerializedBytes,
+    /// The number of values, i.e. 1 for every `null`, boolean, number and
+    /// string, plus 1 for every array and object on top of their contents.
+    /// Object keys and the length of strings don't count.
+    NodeCount,
+}
+
+impl ComplexityModel {
+    /// The complexity of a [`serde_json::Value`].
+    pub(crate) fn of_value(self, value: &Value) -> f64 {
+        match self {
+            Self::SerializedBytes => {
+                let mut counter = ByteCounter(0);
+                serde_json::to_writer(&mut counter, value)
+                    .expect("writing to a `ByteCounter` never fails");
+                counter.0 as f64
+            }
+            Self::NodeCount => match value {
+                Value::Array(array) => array
+                    .iter()
+                    .fold(1.0, |acc, next| acc + self.of_value(next)),
+                Value::Object(object) => object
+                    .values()
+                    .fold(1.0, |acc, next| acc + self.of_value(next)),
+                _ => 1.0,
+            },
+        }
+    }
+
+    /// The same as [`of_value`](Self::of_value), but for the internal
+    /// representation. The two agree unless an object has duplicate keys,
+    /// which a [`serde_json::Value`] can't hold.
+    pub(crate) fn of_internal(self, value: &InternalJsonValue) -> f64 {
+        match value {
+            InternalJsonValue::Null => self.scalar(4),
+            InternalJsonValue::Bool { inner: true } => self.scalar(4),
+            InternalJsonValue::Bool { inner: false } => self.scalar(5),
+            InternalJsonValue::Number { inner } => self.of_number(inner),
+            InternalJsonValue::String { inner } => self.of_string(inner),
+            InternalJsonValue::Array { inner } => self.container(
+                inner.iter().map(|next| self.of_internal(next)).sum(),
+                inner.len(),
+            ),
+            InternalJsonValue::Object { inner } => self.container(
+                inner
+                    .iter()
+                    .map(|(key, value)| self.of_key(key) + self.of_internal(value))
+                    .sum(),
+                inner.len(),
+            ),
+        }
+    }
+
+    pub(crate) fn of_number(self, number: &InternalJsonNumber) -> f64 {
+        let len = match number {
+            InternalJsonNumber::PosInt { inner } => digits(*inner),
+            InternalJsonNumber::NegInt { inner } if *inner < 0 => 1 + digits(inner.unsigned_abs()),
+            InternalJsonNumber::NegInt { inner } => digits(*inner as u64),
+            #[cfg(feature = "arbitrary_precision")]
+            InternalJsonNumber::Decimal { inner } => inner.len(),
+            InternalJsonNumber::Float { .. } => {
+                let mut counter = ByteCounter(0);
+                write!(counter, "{}", map_internal_number_to_serde(number.clone()))
+                    .expect("writing to a `ByteCounter` never fails");
+                counter.0
+            }
+        };
+        self.scalar(len)
+    }
+
+    pub(crate) fn of_string(self, string: &str) -> f64 {
+        self.scalar(serialized_string_len(string))
+    }
+
+    /// The complexity an object member adds on top of its value's: the key,
+    /// followed by a colon.
+    pub(crate) fn of_key(self, key: &str) -> f64 {
+        match self {
+            Self::SerializedBytes => (serialized_string_len(key) + 1) as f64,
+            Self::NodeCount => 0.0,
+        }
+    }
+
+    /// The complexity of an array or object whose `len` elements or members
+    /// have a total complexity of `contents`: the brackets and the commas
+    /// between the elements or members.
+    pub(crate) fn container(self, contents: f64, len: usize) -> f64 {
+        match self {
+            Self::SerializedBytes => 2.0 + len.saturating_sub(1) as f64 + contents,
+            Self::NodeCount => 1.0 + contents,
+        }
+    }
+
+    /// The complexity of the `len` bytes of `null`, a boolean, a number or a
+    /// string.
+    fn scalar(self, len: usize) -> f64 {
+        match self {
+            Self::SerializedBytes => len as f64,
+            Self::NodeCount => 1.0,
+        }
+    }
+}
+
+/// The length of `string` once quoted and escaped by `serde_json`, which
+/// escapes `"`, `\` and the control characters below U+0020 (with a short
+/// form for the common ones and `\u00XX` for the others).
+fn serialized_string_len(string: &str) -> usize {
+    let mut len = 2 + string.len();
+    // every byte of a multi-byte character is at least 0x80, so only ASCII
+    // characters need to be looked at
+    for byte in string.bytes() {
+        match byte {
+            b'"' | b'\\' | 0x08 | 0x0c | b'\n' | b'\r' | b'\t' => len += 1,
+            0x00..=0x1f => len += 5,
+            _ => {}
+        }
+    }
+    len
+}
+
+/// The number of decimal digits of `n`.
+fn digits(n: u64) -> usize {
+    n.checked_ilog10().map_or(1, |log| log as usize + 1)
+}
+
+/// Counts the bytes written to it, without storing them.
+struct ByteCounter(usize);
+
+impl io::Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl fmt::Write for ByteCounter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0 += s.len();
+        Ok(())
+    }
+}
@@ -0,0 +1,321 @@
+use std::ops::BitOr;
+
+use serde_json::Value;
+
+use crate::ComplexityModel;
+
+/// Controls the shape of the values produced by
+/// [`json_value_mutator_with_config`](crate::json_value_mutator_with_config).
+///
+/// By default no limits are applied (this is the configuration used by
+/// [`json_value_mutator`](crate::json_value_mutator)). Inputs which exceed the
+/// limits (e.g. those loaded from an existing corpus) are rejected by the
+/// mutator.
+///
+/// ```
+/// use fuzzcheck_serde_json_generator::{json_value_mutator_with_config, JsonValueMutatorConfig};
+///
+/// let mutator = json_value_mutator_with_config(
+///     JsonValueMutatorConfig::new()
+///         .max_depth(4)
+///         .max_array_len(16)
+///         .max_object_len(8)
+///         .max_string_len(64),
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct JsonValueMutatorConfig {
+    pub(crate) max_depth: usize,
+    pub(crate) max_array_len: usize,
+    pub(crate) max_object_len: usize,
+    pub(crate) max_string_len: usize,
+    pub(crate) root_kinds: ValueKinds,
+    pub(crate) array_element_kinds: ValueKinds,
+    pub(crate) object_member_kinds: ValueKinds,
+    pub(crate) key_dictionary: Vec<(String, f64)>,
+    pub(crate) arbitrary_key_probability: f64,
+    pub(crate) value_dictionary: Vec<Value>,
+    pub(crate) interesting_values: bool,
+    pub(crate) value_dictionary_probability: f64,
+    pub(crate) complexity_model: ComplexityModel,
+    pub(crate) deviation_probability: f64,
+}
+
+impl Default for JsonValueMutatorConfig {
+    fn default() -> Self {
+        Self {
+            max_depth: usize::MAX,
+            max_array_len: usize::MAX,
+            max_object_len: usize::MAX,
+            max_string_len: usize::MAX,
+            root_kinds: ValueKinds::ALL,
+            array_element_kinds: ValueKinds::ALL,
+            object_member_kinds: ValueKinds::ALL,
+            key_dictionary: Vec::new(),
+            arbitrary_key_probability: 0.1,
+            value_dictionary: Vec::new(),
+            interesting_values: true,
+            value_dictionary_probability: 0.1,
+            complexity_model: ComplexityModel::default(),
+            deviation_probability: 0.01,
+        }
+    }
+}
+
+impl JsonValueMutatorConfig {
+    /// Creates a configuration without any limits.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The maximum number of arrays and objects which may be nested inside one
+    /// another. For example `[]` has a depth of 1 and `{"a": [1]}` has a depth
+    /// of 2. If this is set to 0 only `null`, booleans, numbers and strings
+    /// will be generated.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// The maximum number of elements in an array.
+    pub fn max_array_len(mut self, max_array_len: usize) -> Self {
+        self.max_array_len = max_array_len;
+        self
+    }
+
+    /// The maximum number of members in an object.
+    pub fn max_object_len(mut self, max_object_len: usize) -> Self {
+        self.max_object_len = max_object_len;
+        self
+    }
+
+    /// The maximum number of `char`s in a string. This applies to both string
+    /// values and object keys.
+    pub fn max_string_len(mut self, max_string_len: usize) -> Self {
+        self.max_string_len = max_string_len;
+        self
+    }
+
+    /// The kinds of value which may be generated at the top level.
+    ///
+    /// # Panics
+    ///
+    /// Creating the mutator will panic if `kinds` is empty, or if it only
+    /// contains arrays and/or objects while [`max_depth`](Self::max_depth) is
+    /// 0.
+    pub fn root_kinds(mut self, kinds: ValueKinds) -> Self {
+        self.root_kinds = kinds;
+        self
+    }
+
+    /// The kinds of value which may be generated as the elements of an array.
+    /// If this only contains arrays and/or objects, then arrays at the maximum
+    /// depth will be empty.
+    ///
+    /// # Panics
+    ///
+    /// Creating the mutator will panic if `kinds` is empty.
+    pub fn array_element_kinds(mut self, kinds: ValueKinds) -> Self {
+        self.array_element_kinds = kinds;
+        self
+    }
+
+    /// The kinds of value which may be generated as the values of an object's
+    /// members. If this only contains arrays and/or objects, then objects at
+    /// the maximum depth will be empty.
+    ///
+    /// # Panics
+    ///
+    /// Creating the mutator will panic if `kinds` is empty.
+    pub fn object_member_kinds(mut self, kinds: ValueKinds) -> Self {
+        self.object_member_kinds = kinds;
+        self
+    }
+
+    /// Adds keys which object members should usually be given (e.g. the field
+    /// names the program being fuzzed looks for). Every key is equally likely
+    /// to be picked; see
+    /// [`weighted_key_dictionary`](Self::weighted_key_dictionary) to change
+    /// this. Keys longer than [`max_string_len`](Self::max_string_len) are
+    /// ignored.
+    ///
+    /// ```
+    /// use fuzzcheck_serde_json_generator::JsonValueMutatorConfig;
+    ///
+    /// let config = JsonValueMutatorConfig::new().key_dictionary(["id", "type", "name"]);
+    /// ```
+    pub fn key_dictionary<S: Into<String>>(self, keys: impl IntoIterator<Item = S>) -> Self {
+        self.weighted_key_dictionary(keys.into_iter().map(|key| (key, 1.0)))
+    }
+
+    /// Adds keys which object members should usually be given, each with a
+    /// weight determining how likely it is to be picked relative to the other
+    /// keys in the dictionary.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a weight is not a positive, finite number.
+    pub fn weighted_key_dictionary<S: Into<String>>(
+        mut self,
+        keys: impl IntoIterator<Item = (S, f64)>,
+    ) -> Self {
+        for (key, weight) in keys {
+            assert!(
+                weight > 0.0 && weight.is_finite(),
+                "the weight of a key must be a positive, finite number"
+            );
+            self.key_dictionary.push((key.into(), weight));
+        }
+        self
+    }
+
+    /// The probability of generating an arbitrary key rather than one from the
+    /// key dictionary (the default is 0.1). This has no effect if the
+    /// dictionary is empty.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `probability` is not between 0 and 1.
+    pub fn arbitrary_key_probability(mut self, probability: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&probability),
+            "the probability must be between 0 and 1"
+        );
+        self.arbitrary_key_probability = probability;
+        self
+    }
+
+    /// Adds values which should sometimes be generated instead of arbitrary
+    /// ones (e.g. magic numbers or strings the program being fuzzed treats
+    /// specially). These are only used at positions where they respect the
+    /// rest of the configuration.
+    ///
+    /// ```
+    /// use fuzzcheck_serde_json_generator::JsonValueMutatorConfig;
+    /// use serde_json::json;
+    ///
+    /// let config = JsonValueMutatorConfig::new().value_dictionary([json!(42), json!("admin")]);
+    /// ```
+    pub fn value_dictionary(mut self, values: impl IntoIterator<Item = Value>) -> Self {
+        self.value_dictionary.extend(values);
+        self
+    }
+
+    /// Whether to add a built-in set of values which often break programs
+    /// consuming JSON to the value dictionary (this is enabled by default).
+    /// This includes the boundaries of the integer types, integers which an
+    /// `f64` cannot represent exactly, `-0.0`, extreme floats, and strings
+    /// containing `NUL`, escapes, unusual Unicode or a lot of characters.
+    pub fn interesting_values(mut self, enabled: bool) -> Self {
+        self.interesting_values = enabled;
+        self
+    }
+
+    /// The probability of picking a value from the value dictionary when a
+    /// value is generated or a scalar is mutated (the default is 0.1).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `probability` is not between 0 and 1.
+    pub fn value_dictionary_probability(mut self, probability: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&probability),
+            "the probability must be between 0 and 1"
+        );
+        self.value_dictionary_probability = probability;
+        self
+    }
+
+    /// How the complexity of the generated values is measured (the default is
+    /// [`ComplexityModel::SerializedBytes`]).
+    pub fn complexity_model(mut self, model: ComplexityModel) -> Self {
+        self.complexity_model = model;
+        self
+    }
+
+    /// The probability that
+    /// [`json_example_mutator_with_config`](crate::json_example_mutator_with_config)
+    /// generates a value which doesn't have the shape of the examples, at any
+    /// given position, or lets a mutation make a value stop having it (the
+    /// default is 0.01). Other mutators ignore this.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `probability` is not between 0 and 1.
+    pub fn deviation_probability(mut self, probability: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&probability),
+            "the probability must be between 0 and 1"
+        );
+        self.deviation_probability = probability;
+        self
+    }
+}
+
+/// A set of kinds of JSON value, used to restrict what
+/// [`JsonValueMutatorConfig`] generates at a given position. Sets can be
+/// combined with `|`.
+///
+/// ```
+/// use fuzzcheck_serde_json_generator::{JsonValueMutatorConfig, ValueKinds};
+///
+/// // objects whose members are all strings or numbers
+/// let config = JsonValueMutatorConfig::new()
+///     .max_depth(1)
+///     .root_kinds(ValueKinds::OBJECT)
+///     .object_member_kinds(ValueKinds::STRING | ValueKinds::NUMBER);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ValueKinds(u8);
+
+impl ValueKinds {
+    pub const NULL: Self = Self(1 << 0);
+    pub const BOOL: Self = Self(1 << 1);
+    pub const NUMBER: Self = Self(1 << 2);
+    pub const STRING: Self = Self(1 << 3);
+    pub const ARRAY: Self = Self(1 << 4);
+    pub const OBJECT: Self = Self(1 << 5);
+
+    /// The empty set.
+    pub const NONE: Self = Self(0);
+    /// `null`, booleans, numbers and strings.
+    pub const SCALARS: Self = Self(Self::NULL.0 | Self::BOOL.0 | Self::NUMBER.0 | Self::STRING.0);
+    /// Arrays and objects.
+    pub const CONTAINERS: Self = Self(Self::ARRAY.0 | Self::OBJECT.0);
+    /// Every kind of value.
+    pub const ALL: Self = Self(Self::SCALARS.0 | Self::CONTAINERS.0);
+
+    /// Returns `true` if every kind in `other` is also in `self`.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns `true` if `self` and `other` have at least one kind in common.
+    pub const fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// The kinds in `self` which are not in `other`.
+    pub const fn difference(self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+
+    /// Iterates over each kind in the set as a set containing just that kind.
+    pub(crate) fn iter(self) -> impl Iterator<Item = Self> {
+        (0..6)
+            .map(|bit| Self(1 << bit))
+            .filter(move |kind| self.contains(*kind))
+    }
+}
+
+impl BitOr for ValueKinds {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
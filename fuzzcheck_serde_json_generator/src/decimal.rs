@@ -0,0 +1,130 @@
+//! Number literals of any length, which `serde_json` only preserves with its
+//! `arbitrary_precision` feature.
+
+use fuzzcheck::mutators::bool::BoolMutator;
+use fuzzcheck::mutators::character_classes::CharacterMutator;
+use fuzzcheck::mutators::map::MapMutator;
+use fuzzcheck::mutators::vector::VecMutator;
+use fuzzcheck::{make_mutator, DefaultMutator, Mutator};
+
+/// The most digits generated before the decimal point, and after it.
+const MAX_MANTISSA_DIGITS: usize = 64;
+
+/// The most digits generated in the exponent.
+const MAX_EXPONENT_DIGITS: usize = 8;
+
+pub(crate) type DecimalLiteralMutator = impl Mutator<String>;
+
+type DigitsMutator = impl Mutator<Vec<char>>;
+
+fn digits_mutator(min_len: usize, max_len: usize) -> DigitsMutator {
+    VecMutator::new(CharacterMutator::new(vec!['0'..='9']), min_len..=max_len)
+}
+
+/// Generates JSON number literals, such as `-0012` (which is printed as
+/// `-12`), `3.14159265358979323846264338327950288` or `1e+400`.
+///
+/// The literals are written the way `serde_json` writes the numbers it reads,
+/// i.e. with a lowercase `e` and an explicit sign in the exponent, so that they
+/// survive being printed and parsed again.
+pub(crate) fn decimal_literal_mutator() -> DecimalLiteralMutator {
+    MapMutator::new(
+        DecimalParts::default_mutator(),
+        |literal: &String| DecimalParts::parse(literal),
+        DecimalParts::to_literal,
+        |literal, _| literal.len() as f64,
+    )
+}
+
+/// The parts of a number literal, each of which is mutated independently.
+#[derive(Clone)]
+struct DecimalParts {
+    negative: bool,
+    integer: Vec<char>,
+    fraction: Vec<char>,
+    negative_exponent: bool,
+    exponent: Vec<char>,
+}
+
+make_mutator! {
+    name: DecimalPartsMutator,
+    default: true,
+    type: struct DecimalParts {
+        #[field_mutator(BoolMutator)]
+        negative: bool,
+        #[field_mutator(DigitsMutator = {digits_mutator(1, MAX_MANTISSA_DIGITS)})]
+        integer: Vec<char>,
+        #[field_mutator(DigitsMutator = {digits_mutator(0, MAX_MANTISSA_DIGITS)})]
+        fraction: Vec<char>,
+        #[field_mutator(BoolMutator)]
+        negative_exponent: bool,
+        #[field_mutator(DigitsMutator = {digits_mutator(0, MAX_EXPONENT_DIGITS)})]
+        exponent: Vec<char>,
+    }
+}
+
+impl DecimalParts {
+    /// Splits a JSON number literal into its parts, or returns `None` if it
+    /// isn't one (or has more digits than would be generated).
+    fn parse(literal: &str) -> Option<Self> {
+        let (negative, literal) = match literal.strip_prefix('-') {
+            Some(literal) => (true, literal),
+            None => (false, literal),
+        };
+        let (mantissa, exponent) = match literal.split_once('e') {
+            Some((mantissa, exponent)) => (mantissa, Some(exponent)),
+            None => (literal, None),
+        };
+        let (integer, fraction) = match mantissa.split_once('.') {
+            Some((_, "")) => return None,
+            Some((integer, fraction)) => (integer, fraction),
+            None => (mantissa, ""),
+        };
+        let min_exponent_len = exponent.is_some() as usize;
+        let (negative_exponent, exponent) = match exponent {
+            Some(exponent) => match (exponent.strip_prefix('+'), exponent.strip_prefix('-')) {
+                (Some(exponent), _) => (false, exponent),
+                (_, Some(exponent)) => (true, exponent),
+                _ => return None,
+            },
+            None => (false, ""),
+        };
+        let digits = |digits: &str, min_len: usize, max_len: usize| {
+            (digits.bytes().all(|byte| byte.is_ascii_digit())
+                && (min_len..=max_len).contains(&digits.len()))
+            .then(|| digits.chars().collect::<Vec<_>>())
+        };
+        if integer.len() > 1 && integer.starts_with('0') {
+            return None;
+        }
+        Some(Self {
+            negative,
+            integer: digits(integer, 1, MAX_MANTISSA_DIGITS)?,
+            fraction: digits(fraction, 0, MAX_MANTISSA_DIGITS)?,
+            negative_exponent,
+            exponent: digits(exponent, min_exponent_len, MAX_EXPONENT_DIGITS)?,
+        })
+    }
+
+    /// Joins the parts into a JSON number literal, dropping the leading zeros
+    /// of the integer part, which JSON doesn't allow.
+    fn to_literal(&self) -> String {
+        let mut literal = String::new();
+        if self.negative {
+            literal.push('-');
+        }
+        match self.integer.iter().position(|digit| *digit != '0') {
+            Some(idx) => literal.extend(&self.integer[idx..]),
+            None => literal.push('0'),
+        }
+        if !self.fraction.is_empty() {
+            literal.push('.');
+            literal.extend(&self.fraction);
+        }
+        if !self.exponent.is_empty() {
+            literal.push_str(if self.negative_exponent { "e-" } else { "e+" });
+            literal.extend(&self.exponent);
+        }
+        literal
+    }
+}
@@ -0,0 +1,91 @@
+use fuzzcheck::mutators::vose_alias::VoseAlias;
+use serde_json::{json, Value};
+
+/// A list of values to pick from at random, each with its own weight.
+pub(crate) struct Dictionary<T> {
+    entries: Vec<T>,
+    alias: VoseAlias,
+}
+
+impl<T> Dictionary<T> {
+    /// Returns `None` if there are no entries.
+    pub(crate) fn new(weighted_entries: impl IntoIterator<Item = (T, f64)>) -> Option<Self> {
+        let (entries, weights): (Vec<_>, Vec<_>) = weighted_entries.into_iter().unzip();
+        (!entries.is_empty()).then(|| Self {
+            entries,
+            alias: VoseAlias::new(weights),
+        })
+    }
+
+    pub(crate) fn sample(&self) -> &T {
+        &self.entries[self.alias.sample()]
+    }
+}
+
+/// Scalars which are likely to find bugs in programs consuming JSON: the
+/// boundaries of the integer types, integers which cannot be represented
+/// exactly as an `f64`, extreme floats and strings which are awkward to
+/// escape or decode.
+///
+/// A [`Value`] can only hold valid UTF-8, so it is impossible to include a
+/// string containing a lone surrogate. Instead there are strings containing
+/// the text of a lone surrogate escape (which catches programs which unescape
+/// strings twice) and the characters on either side of the surrogate range.
+pub(crate) fn interesting_values() -> Vec<Value> {
+    const MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
+
+    vec![
+        json!(0),
+        json!(1),
+        json!(-1),
+        json!(i64::MIN),
+        json!(i64::MAX),
+        json!(i64::MAX as u64 + 1),
+        json!(u64::MAX),
+        json!(u32::MAX),
+        json!(i32::MIN),
+        json!(MAX_SAFE_INTEGER),
+        json!(MAX_SAFE_INTEGER + 1),
+        json!(MAX_SAFE_INTEGER + 2),
+        json!(-(MAX_SAFE_INTEGER as i64)),
+        json!(-(MAX_SAFE_INTEGER as i64) - 1),
+        json!(-(MAX_SAFE_INTEGER as i64) - 2),
+        json!(0.0),
+        json!(-0.0),
+        json!(0.1),
+        json!(1e308),
+        json!(-1e308),
+        json!(f64::MAX),
+        json!(f64::MIN),
+        json!(f64::MIN_POSITIVE),
+        json!(f64::EPSILON),
+        json!(5e-324),
+        json!(9007199254740992.0),
+        json!(""),
+        json!("\0"),
+        json!("a\0b"),
+        json!("\"\\/\u{8}\u{c}\n\r\t"),
+        json!("\u{1f}\u{7f}"),
+        json!("\\ud800"),
+        json!("\\udfff"),
+        json!("\\ud83d\\ude00"),
+        json!("\u{d7ff}\u{e000}"),
+        json!("\u{fffd}"),
+        json!("\u{feff}"),
+        json!("\u{fffe}\u{ffff}"),
+        json!("\u{10ffff}"),
+        json!("\u{1f600}"),
+        json!("\u{2028}\u{2029}"),
+        json!("e\u{301}"),
+        json!("0"),
+        json!("-0"),
+        json!("1e400"),
+        json!("NaN"),
+        json!("true"),
+        json!("null"),
+        json!("a".repeat(1 << 10)),
+        // as long as fits in fuzzcheck's default maximum complexity (4096),
+        // leaving room for the arrays or objects around it
+        json!("a".repeat(4_000)),
+    ]
+}
@@ -0,0 +1,118 @@
+//! Infers a [`Shape`] from example documents, for
+//! [`json_example_mutator`](crate::json_example_mutator).
+
+use serde_json::Value;
+
+use crate::shape::{Constraints, Shape, ShapeBuilder, ShapeId, ShapeNode, ANY};
+use crate::ValueKinds;
+
+/// What the examples have in common at a given position.
+struct Template {
+    kinds: ValueKinds,
+    /// Whether a number which isn't an integer was found.
+    fractions: bool,
+    /// The elements of every array.
+    items: Option<Box<Template>>,
+    /// The number of objects found.
+    objects: usize,
+    /// The members of every object, in the order their key was first found,
+    /// along with the number of objects they were found in.
+    members: Vec<(String, Template, usize)>,
+}
+
+impl Template {
+    fn new() -> Self {
+        Self {
+            kinds: ValueKinds::NONE,
+            fractions: false,
+            items: None,
+            objects: 0,
+            members: Vec::new(),
+        }
+    }
+
+    fn add(&mut self, value: &Value) {
+        match value {
+            Value::Null => self.kinds = self.kinds | ValueKinds::NULL,
+            Value::Bool(_) => self.kinds = self.kinds | ValueKinds::BOOL,
+            Value::Number(number) => {
+                self.kinds = self.kinds | ValueKinds::NUMBER;
+                self.fractions |= !(number.is_u64() || number.is_i64());
+            }
+            Value::String(_) => self.kinds = self.kinds | ValueKinds::STRING,
+            Value::Array(array) => {
+                self.kinds = self.kinds | ValueKinds::ARRAY;
+                let items = self.items.get_or_insert_with(|| Box::new(Self::new()));
+                for element in array {
+                    items.add(element);
+                }
+            }
+            Value::Object(object) => {
+                self.kinds = self.kinds | ValueKinds::OBJECT;
+                self.objects += 1;
+                for (key, value) in object {
+                    let idx = match self.members.iter().position(|(other, ..)| other == key) {
+                        Some(idx) => idx,
+                        None => {
+                            self.members.push((key.clone(), Self::new(), 0));
+                            self.members.len() - 1
+                        }
+                    };
+                    let (_, template, count) = &mut self.members[idx];
+                    template.add(value);
+                    *count += 1;
+                }
+            }
+        }
+    }
+
+    /// Adds the node matching the values of the template to `builder`,
+    /// wrapped in a [`ShapeNode::Usually`] node.
+    fn build(&self, builder: &mut ShapeBuilder, probability: f64) -> ShapeId {
+        let mut constraints = Constraints::of_kinds(self.kinds, ANY, ANY);
+        // a number is only expected to be an integer if every example is one
+        constraints.integer = self.kinds.contains(ValueKinds::NUMBER) && !self.fractions;
+        if let Some(items) = &self.items {
+            constraints.items = items.build(builder, probability);
+        }
+        constraints.properties = self
+            .members
+            .iter()
+            .map(|(key, template, _)| (key.clone(), template.build(builder, probability)))
+            .collect();
+        constraints.required = self
+            .members
+            .iter()
+            .filter(|(_, _, count)| *count == self.objects)
+            .map(|(key, ..)| key.clone())
+            .collect();
+        constraints.additional_properties = None;
+        let expected = builder.add(ShapeNode::Constraints(constraints));
+        builder.add(ShapeNode::Usually {
+            expected,
+            probability,
+        })
+    }
+}
+
+/// The shape of the examples: the kinds of value found at each position, the
+/// members of each object (which are required if every example has them) and
+/// the elements of each array. Values of any other shape match it, but values
+/// of this shape are generated with probability `1 - deviation_probability`
+/// at every position.
+pub(crate) fn infer<'a>(
+    examples: impl IntoIterator<Item = &'a Value>,
+    deviation_probability: f64,
+) -> Shape {
+    let mut template = Template::new();
+    for example in examples {
+        template.add(example);
+    }
+    assert!(
+        !template.kinds.is_empty(),
+        "at least one example must be given"
+    );
+    let mut builder = ShapeBuilder::new();
+    let root = template.build(&mut builder, 1.0 - deviation_probability);
+    builder.build(root)
+}
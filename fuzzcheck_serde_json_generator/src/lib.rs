@@ -1,26 +1,65 @@
 //! This crate contains a Fuzzcheck mutator for [`serde_json::Value`]. The main
 //! item of relevance is [`json_value_mutator`].
+//!
+//! By default the members of the objects in a [`serde_json::Value`] are sorted
+//! by key. Enabling the `preserve_order` feature (which enables the feature of
+//! the same name of `serde_json`) keeps them in the order they were generated
+//! in, or read from the corpus.
+//!
+//! Enabling the `arbitrary_precision` feature (which also enables the feature
+//! of the same name of `serde_json`) generates numbers of any length and
+//! precision, such as `-31415926535897932384626433832795028841971e-40` or
+//! `1e+400`, which are kept as written instead of being rounded to an `f64`.
 
 #![feature(type_alias_impl_trait)]
 #![feature(coverage_attribute)]
 
+mod complexity;
+mod config;
+#[cfg(feature = "arbitrary_precision")]
+mod decimal;
+mod dictionary;
+mod infer;
+mod mutator;
+mod ndjson;
+mod near_miss;
+mod schema;
+mod serialization;
+mod shape;
+mod typed;
+
+pub use complexity::ComplexityModel;
+pub use config::{JsonValueMutatorConfig, ValueKinds};
+pub use near_miss::{SchemaViolation, ViolationKind};
+pub use schema::SchemaError;
+
+#[cfg(feature = "arbitrary_precision")]
+use decimal::DecimalLiteralMutator;
 use fuzzcheck::mutators::bool::BoolMutator;
-use fuzzcheck::mutators::integer::U64Mutator;
-use fuzzcheck::mutators::recursive::RecurToMutator;
-use fuzzcheck::mutators::string::string_mutator;
-use fuzzcheck::mutators::string::StringMutator;
+use fuzzcheck::mutators::integer::{I64Mutator, U64Mutator};
+use fuzzcheck::mutators::integer_within_range::U8WithinRangeMutator;
 use fuzzcheck::mutators::tuples::{Tuple2, Tuple2Mutator, TupleMutatorWrapper};
-use fuzzcheck::DefaultMutator;
-
-use fuzzcheck::{
-    make_mutator,
-    mutators::{map::MapMutator, vector::VecMutator},
-    Mutator,
-};
+use fuzzcheck::mutators::vector::VecMutator;
+use fuzzcheck::{make_mutator, mutators::map::MapMutator, Mutator};
+use mutator::InternalJsonValueMutator;
+use near_miss::{InternalNearMissMutator, NearMiss};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use serde_json::{Number, Value};
+use typed::DeserializingMutator;
 
 pub type ValueMutator = impl Mutator<Value>;
 
+pub type NearMissMutator = impl Mutator<(Value, SchemaViolation)>;
+
+pub type JsonStringMutator = impl Mutator<String>;
+
+pub type NdjsonValueMutator = impl Mutator<(String, Vec<Value>)>;
+
+pub type JsonTypedMutator<T: Clone + Serialize + DeserializeOwned + 'static> = impl Mutator<T>;
+
+type FiniteF64Mutator = impl Mutator<f64>;
+
 /// A Fuzzcheck mutator for [`serde_json::Value`].
 ///
 /// Example usage with Fuzzcheck (see the
@@ -43,77 +82,491 @@ pub type ValueMutator = impl Mutator<Value>;
 /// assert!(!result.found_test_failure)
 /// ```
 pub fn json_value_mutator() -> ValueMutator {
+    json_value_mutator_with_config(JsonValueMutatorConfig::default())
+}
+
+/// A Fuzzcheck mutator for [`serde_json::Value`] which only generates values
+/// within the limits set by `config`.
+pub fn json_value_mutator_with_config(config: JsonValueMutatorConfig) -> ValueMutator {
+    value_mutator(InternalJsonValueMutator::new(config))
+}
+
+/// A Fuzzcheck mutator for JSON documents, serialized as compact strings.
+///
+/// This generates the same values as [`json_value_mutator_with_config`], but
+/// unlike a [`serde_json::Value`] the documents may contain objects with
+/// duplicate keys (such as `{"a":1,"a":2}`), and the members of objects keep
+/// the order they were generated in. This is meant to test how a parser
+/// handles them. Duplicate keys are generated on purpose, by mutating an
+/// object to add a member with the same key as an existing one.
+///
+/// Strings which aren't valid JSON (e.g. from an existing corpus) are rejected
+/// by the mutator.
+///
+/// ```
+/// use fuzzcheck_serde_json_generator::{json_string_mutator_with_config, JsonValueMutatorConfig};
+///
+/// let mutator =
+///     json_string_mutator_with_config(JsonValueMutatorConfig::new().key_dictionary(["a", "b"]));
+/// ```
+pub fn json_string_mutator() -> JsonStringMutator {
+    json_string_mutator_with_config(JsonValueMutatorConfig::default())
+}
+
+/// The same as [`json_string_mutator`], but only generates documents within
+/// the limits set by `config`.
+pub fn json_string_mutator_with_config(config: JsonValueMutatorConfig) -> JsonStringMutator {
     MapMutator::new(
-        InternalJsonValue::default_mutator(),
-        |value: &Value| map_serde_json_to_internal(value.clone()),
-        |internal_json_value| map_internal_jv_to_serde(internal_json_value.clone()),
-        |input, _| calculate_output_cplx(input),
+        InternalJsonValueMutator::new(config).with_duplicate_keys(),
+        |string: &String| serde_json::from_str(string).ok(),
+        |internal_json_value| {
+            serde_json::to_string(internal_json_value)
+                .expect("serializing an `InternalJsonValue` never fails")
+        },
+        // the complexity of the internal value already counts duplicate keys
+        |_, cplx| cplx,
     )
 }
 
-// each byte = 1 unit of complexity (?)
-fn calculate_output_cplx(input: &Value) -> f64 {
-    match input {
-        Value::Null => 1.0,
-        Value::Bool(_) => 1.0,
-        Value::Number(_) => {
-            // 64-bit
-            1.0 + 8.0
-        }
-        Value::String(string) => 1.0 + string.len() as f64,
-        Value::Array(array) => array
-            .iter()
-            .fold(1.0, |acc, next| acc + calculate_output_cplx(next)),
-        Value::Object(object) => object.iter().fold(1.0, |acc, (key, value)| {
-            acc + 1.0 + key.len() as f64 + calculate_output_cplx(value) as f64
-        }),
+/// A Fuzzcheck mutator for streams of JSON documents, such as
+/// [NDJSON](https://github.com/ndjson/ndjson-spec) (or JSON Lines) logs, in
+/// which each document is written on its own line. It generates the stream
+/// along with the documents in it, each of which is generated by
+/// [`json_value_mutator`]. The lines end with `\n` or `\r\n`, may be
+/// followed by blank lines, and the last one may not end with a line break at
+/// all. Documents are inserted, removed and swapped so that records move
+/// across the boundaries the parser being fuzzed reads them by.
+///
+/// Streams which weren't written this way (e.g. from an existing corpus) are
+/// rejected by the mutator.
+///
+/// ```
+/// use fuzzcheck::Mutator;
+/// use fuzzcheck_serde_json_generator::ndjson_value_mutator;
+///
+/// let ((stream, values), _) = ndjson_value_mutator().random_arbitrary(256.0);
+/// let lines = stream.lines().filter(|line| !line.trim().is_empty());
+/// assert_eq!(lines.count(), values.len());
+/// ```
+pub fn ndjson_value_mutator() -> NdjsonValueMutator {
+    ndjson_value_mutator_with_config(JsonValueMutatorConfig::default())
+}
+
+/// The same as [`ndjson_value_mutator`], but each document is generated
+/// within the limits set by `config`.
+pub fn ndjson_value_mutator_with_config(config: JsonValueMutatorConfig) -> NdjsonValueMutator {
+    let record = TupleMutatorWrapper::<_, Tuple2<Value, u8>>::new(Tuple2Mutator::new(
+        json_value_mutator_with_config(config),
+        U8WithinRangeMutator::new(0..ndjson::SEPARATORS.len() as u8),
+    ));
+    MapMutator::new(
+        TupleMutatorWrapper::<_, Tuple2<Vec<(Value, u8)>, bool>>::new(Tuple2Mutator::new(
+            VecMutator::new(record, 0..=usize::MAX),
+            BoolMutator::default(),
+        )),
+        |(stream, values): &(String, Vec<Value>)| ndjson::read(stream, values),
+        |(records, terminated)| {
+            let values = records.iter().map(|(value, _)| value.clone()).collect();
+            (ndjson::write(records, *terminated), values)
+        },
+        |_, cplx| cplx,
+    )
+}
+
+/// A Fuzzcheck mutator for any type which can be serialized to and
+/// deserialized from JSON, such as the request types of a web service. It
+/// generates [`serde_json::Value`]s (see [`json_value_mutator`]), keeps those
+/// which deserialize into a `T` and deserializes them.
+///
+/// The names of the fields and variants of `T` are added to the key and value
+/// dictionaries, so that objects and strings deserializing into it come up
+/// more often. Values which don't deserialize are generated or mutated again a
+/// bounded number of times, after which the simplest value of `T` (with empty
+/// strings and collections, zeros, `None`s and the first variant of enums) is
+/// generated instead (or the mutation is given up on), so the mutator always
+/// terminates even if `T` validates its fields.
+///
+/// # Panics
+///
+/// Panics if the simplest value of `T` can't be built, e.g. because it
+/// validates its fields, or is an enum which isn't externally tagged. Give the
+/// mutator values of `T` with [`json_typed_mutator_from_examples`] instead.
+///
+/// ```
+/// use fuzzcheck_serde_json_generator::json_typed_mutator;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Clone, Serialize, Deserialize)]
+/// struct Request {
+///     user: String,
+///     limit: Option<u32>,
+///     tags: Vec<String>,
+/// }
+///
+/// let mutator = json_typed_mutator::<Request>();
+/// ```
+pub fn json_typed_mutator<T>() -> JsonTypedMutator<T>
+where
+    T: Clone + Serialize + DeserializeOwned + 'static,
+{
+    json_typed_mutator_with_config(JsonValueMutatorConfig::default())
+}
+
+/// The same as [`json_typed_mutator`], but only generates values within the
+/// limits set by `config`.
+///
+/// # Panics
+///
+/// Panics if the simplest value of `T` can't be built, or isn't within the
+/// limits set by `config`.
+pub fn json_typed_mutator_with_config<T>(config: JsonValueMutatorConfig) -> JsonTypedMutator<T>
+where
+    T: Clone + Serialize + DeserializeOwned + 'static,
+{
+    json_typed_mutator_from_examples_with_config(std::iter::empty(), config)
+}
+
+/// The same as [`json_typed_mutator`], but the values are shaped like the
+/// serialized `examples` (see [`json_example_mutator`]), which are also
+/// generated instead of values which don't deserialize. This is for types
+/// whose simplest value can't be built, or which values generated at random
+/// rarely deserialize into.
+///
+/// ```
+/// use fuzzcheck_serde_json_generator::json_typed_mutator_from_examples;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Clone, Serialize, Deserialize)]
+/// #[serde(try_from = "String", into = "String")]
+/// struct Email(String);
+/// # impl TryFrom<String> for Email {
+/// #     type Error = &'static str;
+/// #     fn try_from(email: String) -> Result<Self, Self::Error> {
+/// #         email.contains('@').then_some(Email(email)).ok_or("not an email")
+/// #     }
+/// # }
+/// # impl From<Email> for String {
+/// #     fn from(email: Email) -> Self {
+/// #         email.0
+/// #     }
+/// # }
+///
+/// let mutator = json_typed_mutator_from_examples([Email("alice@example.com".to_string())]);
+/// ```
+pub fn json_typed_mutator_from_examples<T>(
+    examples: impl IntoIterator<Item = T>,
+) -> JsonTypedMutator<T>
+where
+    T: Clone + Serialize + DeserializeOwned + 'static,
+{
+    json_typed_mutator_from_examples_with_config(examples, JsonValueMutatorConfig::default())
+}
+
+/// The same as [`json_typed_mutator_from_examples`], but only generates
+/// values within the limits set by `config` (the kinds of value in `config`
+/// are ignored if there are examples).
+///
+/// # Panics
+///
+/// Panics if neither the simplest value of `T` nor any of the examples can be
+/// generated within the limits set by `config`.
+pub fn json_typed_mutator_from_examples_with_config<T>(
+    examples: impl IntoIterator<Item = T>,
+    config: JsonValueMutatorConfig,
+) -> JsonTypedMutator<T>
+where
+    T: Clone + Serialize + DeserializeOwned + 'static,
+{
+    let examples = examples
+        .into_iter()
+        .map(|example| serde_json::to_value(example).expect("the example serializes to JSON"))
+        .collect::<Vec<_>>();
+    let mut names = typed::Names::default();
+    let simplest = typed::sample::<T>(&mut names);
+    let config = config
+        .key_dictionary(names.fields)
+        .value_dictionary(names.variants.into_iter().map(Value::from));
+    let inner = if examples.is_empty() {
+        json_value_mutator_with_config(config)
+    } else {
+        json_example_mutator_with_config(examples.clone(), config)
+    };
+    typed_mutator(DeserializingMutator::new(
+        inner,
+        examples.into_iter().chain(simplest),
+    ))
+}
+
+fn typed_mutator<T>(mutator: DeserializingMutator<ValueMutator, T>) -> JsonTypedMutator<T>
+where
+    T: Clone + Serialize + DeserializeOwned + 'static,
+{
+    MapMutator::new(
+        mutator,
+        |typed: &T| serde_json::to_value(typed).ok(),
+        |value| T::deserialize(value).expect("the mutator only generates values which deserialize"),
+        |_, cplx| cplx,
+    )
+}
+
+/// A Fuzzcheck mutator for [`serde_json::Value`] which mostly generates values
+/// with the same shape as the examples, for when writing a JSON Schema is too
+/// much effort.
+///
+/// The shape is inferred from the kinds of value found at each position in
+/// the examples (and whether their numbers are all integers), the keys of
+/// their objects (which are required if every object at that position has
+/// them) and the elements of their arrays. The examples are also added to
+/// the value dictionary. Once in a while a value (or a part of it) doesn't
+/// have that shape, see
+/// [`JsonValueMutatorConfig::deviation_probability`].
+///
+/// # Panics
+///
+/// Panics if there are no examples.
+///
+/// ```
+/// use fuzzcheck_serde_json_generator::json_example_mutator;
+/// use serde_json::json;
+///
+/// let mutator = json_example_mutator([
+///     json!({"id": 1, "name": "a", "tags": ["x"]}),
+///     json!({"id": 2, "tags": [], "parent": 1}),
+/// ]);
+/// ```
+pub fn json_example_mutator(examples: impl IntoIterator<Item = Value>) -> ValueMutator {
+    json_example_mutator_with_config(examples, JsonValueMutatorConfig::default())
+}
+
+/// The same as [`json_example_mutator`], but only generates values within the
+/// limits set by `config` (the kinds of value in `config` are ignored).
+pub fn json_example_mutator_with_config(
+    examples: impl IntoIterator<Item = Value>,
+    config: JsonValueMutatorConfig,
+) -> ValueMutator {
+    let examples = examples.into_iter().collect::<Vec<_>>();
+    let shape = infer::infer(&examples, config.deviation_probability);
+    value_mutator(InternalJsonValueMutator::with_shape(
+        config.value_dictionary(examples),
+        shape,
+    ))
+}
+
+/// A Fuzzcheck mutator for [`serde_json::Value`] which only generates values
+/// matching a JSON Schema, so that they aren't all rejected by a program which
+/// validates its input.
+///
+/// The following keywords are supported: `type`, `enum`, `const`, `minimum`,
+/// `maximum`, `exclusiveMinimum`, `exclusiveMaximum`, `minLength`,
+/// `maxLength`, `items` (with a single schema), `minItems`, `maxItems`,
+/// `properties`, `required`, `additionalProperties`, `oneOf`, `anyOf` and
+/// `$ref` (to a JSON pointer into `schema`, such as `#/$defs/node`). Other
+/// keywords, such as `pattern` or `format`, are ignored, so the values may not
+/// satisfy them.
+///
+/// The keywords next to a `oneOf`, `anyOf` or `$ref` are combined with each
+/// branch or with the referenced schema (e.g. the tighter of two `minimum`s
+/// applies), and [`SchemaError::UnsupportedKeyword`] is returned when they
+/// can't be, such as when both have an `anyOf`.
+///
+/// ```
+/// use fuzzcheck_serde_json_generator::json_schema_mutator;
+/// use serde_json::json;
+///
+/// let mutator = json_schema_mutator(&json!({
+///     "type": "object",
+///     "properties": {
+///         "id": { "type": "integer", "minimum": 1 },
+///         "tags": { "type": "array", "items": { "type": "string" }, "maxItems": 8 }
+///     },
+///     "required": ["id"],
+///     "additionalProperties": false
+/// }))
+/// .unwrap();
+/// ```
+pub fn json_schema_mutator(schema: &Value) -> Result<ValueMutator, SchemaError> {
+    Ok(value_mutator(schema_mutator(schema::compile(schema)?)?))
+}
+
+/// A Fuzzcheck mutator generating values which match a JSON Schema except for
+/// exactly one constraint, along with a description of the violated
+/// constraint. This is meant to test how a program handles inputs which are
+/// almost, but not quite, valid: missing required members, values of the wrong
+/// type, numbers out of range, strings or arrays which are too short or too
+/// long, values not in an `enum` and members forbidden by
+/// `additionalProperties`.
+///
+/// The same keywords as in [`json_schema_mutator`] are supported. Constraints
+/// inside a `oneOf` or `anyOf` with several branches are never violated, since
+/// the value might then match another branch instead.
+///
+/// ```
+/// use fuzzcheck_serde_json_generator::json_schema_near_miss_mutator;
+/// use serde_json::json;
+///
+/// let mutator = json_schema_near_miss_mutator(&json!({
+///     "type": "object",
+///     "properties": { "port": { "type": "integer", "minimum": 1, "maximum": 65535 } },
+///     "required": ["port"],
+///     "additionalProperties": false
+/// }))
+/// .unwrap();
+/// ```
+pub fn json_schema_near_miss_mutator(schema: &Value) -> Result<NearMissMutator, SchemaError> {
+    let shape = schema::compile(schema)?;
+    if !near_miss::has_violable_constraint(&shape) {
+        return Err(SchemaError::NothingToViolate);
     }
+    let mutator = schema_mutator(shape)?;
+    let model = mutator.complexity_model();
+    Ok(MapMutator::new(
+        InternalNearMissMutator::new(mutator)?,
+        |(value, violation): &(Value, SchemaViolation)| {
+            Some(NearMiss::new(
+                map_serde_json_to_internal(value.clone()),
+                violation.clone(),
+            ))
+        },
+        |near_miss| {
+            (
+                map_internal_jv_to_serde(near_miss.broken().clone()),
+                near_miss.violation().clone(),
+            )
+        },
+        move |(value, _), _| model.of_value(value),
+    ))
 }
 
-fn map_serde_json_to_internal(value: Value) -> Option<InternalJsonValue> {
-    match value {
-        Value::Null => Some(InternalJsonValue::Null),
-        Value::Bool(bool) => Some(InternalJsonValue::Bool { inner: bool }),
-        Value::Number(n) => n
-            .as_u64()
-            .map(|number| InternalJsonValue::Number { inner: number }),
-        Value::String(string) => Some(InternalJsonValue::String { inner: string }),
-        Value::Array(array) => {
-            let array = array
-                .into_iter()
-                .map(map_serde_json_to_internal)
-                .collect::<Vec<_>>();
-            if array.iter().all(Option::is_some) {
-                Some(InternalJsonValue::Array {
-                    inner: array.into_iter().map(|item| item.unwrap()).collect(),
-                })
+/// Builds the mutator for a compiled schema, which must have a simplest value
+/// to fall back on when the values it generates at random don't match (e.g.
+/// every one of them matches several branches of a `oneOf`).
+fn schema_mutator(shape: shape::Shape) -> Result<InternalJsonValueMutator, SchemaError> {
+    let mutator = InternalJsonValueMutator::with_shape(JsonValueMutatorConfig::default(), shape);
+    if mutator.simplest(mutator.shape().root(), 0).is_none() {
+        return Err(SchemaError::Unsatisfiable);
+    }
+    Ok(mutator)
+}
+
+fn value_mutator(mutator: InternalJsonValueMutator) -> ValueMutator {
+    let model = mutator.complexity_model();
+    MapMutator::new(
+        mutator,
+        |value: &Value| Some(map_serde_json_to_internal(value.clone())),
+        |internal_json_value| map_internal_jv_to_serde(internal_json_value.clone()),
+        move |input, _| model.of_value(input),
+    )
+}
+
+/// Generates every finite `f64` by mutating its bit pattern.
+///
+/// `serde_json` cannot represent `NaN` or the infinities, so bit patterns which
+/// would produce one of them have the top bit of their exponent cleared (which
+/// always results in a finite number).
+fn finite_f64_mutator() -> FiniteF64Mutator {
+    MapMutator::new(
+        U64Mutator::default(),
+        |float: &f64| float.is_finite().then(|| float.to_bits()),
+        |bits| {
+            let float = f64::from_bits(*bits);
+            if float.is_finite() {
+                float
             } else {
-                None
+                f64::from_bits(*bits & !(1 << 62))
             }
+        },
+        |_, cplx| cplx,
+    )
+}
+
+/// Converts any [`serde_json::Value`] into the internal representation. This
+/// never fails, so that any corpus of JSON documents can be used to seed the
+/// fuzzer.
+fn map_serde_json_to_internal(value: Value) -> InternalJsonValue {
+    match value {
+        Value::Null => InternalJsonValue::Null,
+        Value::Bool(bool) => InternalJsonValue::Bool { inner: bool },
+        Value::Number(n) => InternalJsonValue::Number {
+            inner: map_serde_json_number_to_internal(&n),
+        },
+        Value::String(string) => InternalJsonValue::String { inner: string },
+        Value::Array(array) => InternalJsonValue::Array {
+            inner: array.into_iter().map(map_serde_json_to_internal).collect(),
+        },
+        Value::Object(object) => InternalJsonValue::Object {
+            inner: object
+                .into_iter()
+                .map(|(key, value)| (key, map_serde_json_to_internal(value)))
+                .collect(),
+        },
+    }
+}
+
+#[cfg(not(feature = "arbitrary_precision"))]
+fn map_serde_json_number_to_internal(number: &Number) -> InternalJsonNumber {
+    if let Some(inner) = number.as_u64() {
+        InternalJsonNumber::PosInt { inner }
+    } else if let Some(inner) = number.as_i64() {
+        InternalJsonNumber::NegInt { inner }
+    } else {
+        InternalJsonNumber::Float {
+            inner: number
+                .as_f64()
+                .expect("a `serde_json::Number` which is not an integer is always an `f64`"),
         }
-        Value::Object(object) => Some(InternalJsonValue::Object {
-            inner: {
-                let vec = object
-                    .into_iter()
-                    .map(|(key, value)| (key, map_serde_json_to_internal(value)))
-                    .collect::<Vec<_>>();
-                if vec.iter().all(|(_, o)| o.is_some()) {
-                    vec.into_iter()
-                        .map(|(key, val)| (key, val.unwrap()))
-                        .collect()
-                } else {
-                    return None;
-                }
-            },
-        }),
     }
 }
 
+/// Keeps the literal of every number which isn't an integer written the way
+/// `serde_json` would print it (such as `1.50`, `-0` or `1e+3`), or is too
+/// large for a `u64`.
+#[cfg(feature = "arbitrary_precision")]
+fn map_serde_json_number_to_internal(number: &Number) -> InternalJsonNumber {
+    let number = reparse_number(number);
+    let literal = number.to_string();
+    match (number.as_u64(), number.as_i64()) {
+        (Some(inner), _) if inner.to_string() == literal => InternalJsonNumber::PosInt { inner },
+        (_, Some(inner)) if inner.to_string() == literal => InternalJsonNumber::NegInt { inner },
+        _ => InternalJsonNumber::Decimal { inner: literal },
+    }
+}
+
+fn map_internal_number_to_serde(internal: InternalJsonNumber) -> Number {
+    match internal {
+        InternalJsonNumber::PosInt { inner } => Number::from(inner),
+        InternalJsonNumber::NegInt { inner } => Number::from(inner),
+        #[cfg(not(feature = "arbitrary_precision"))]
+        InternalJsonNumber::Float { inner } => {
+            Number::from_f64(inner).expect("the float mutator only generates finite numbers")
+        }
+        #[cfg(feature = "arbitrary_precision")]
+        InternalJsonNumber::Float { inner } => reparse_number(
+            &Number::from_f64(inner).expect("the float mutator only generates finite numbers"),
+        ),
+        #[cfg(feature = "arbitrary_precision")]
+        InternalJsonNumber::Decimal { inner } => inner
+            .parse()
+            .expect("the decimal mutator only generates valid number literals"),
+    }
+}
+
+/// Writes a number the way `serde_json` writes the numbers it reads. A number
+/// made from an `f64` keeps the literal it was printed as (such as `1e300`),
+/// which wouldn't be equal to the same number once printed and read again
+/// (`1e+300`).
+#[cfg(feature = "arbitrary_precision")]
+fn reparse_number(number: &Number) -> Number {
+    number
+        .to_string()
+        .parse()
+        .expect("a `serde_json::Number` is printed as a valid number literal")
+}
+
 fn map_internal_jv_to_serde(internal: InternalJsonValue) -> Value {
     match internal {
         InternalJsonValue::Null => Value::Null,
         InternalJsonValue::Bool { inner } => Value::Bool(inner),
-        InternalJsonValue::Number { inner } => Value::Number(Number::from(inner)),
+        InternalJsonValue::Number { inner } => Value::Number(map_internal_number_to_serde(inner)),
         InternalJsonValue::String { inner } => Value::String(inner),
         InternalJsonValue::Array { inner } => {
             Value::Array(inner.into_iter().map(map_internal_jv_to_serde).collect())
@@ -134,7 +587,7 @@ enum InternalJsonValue {
         inner: bool,
     },
     Number {
-        inner: u64,
+        inner: InternalJsonNumber,
     },
     String {
         inner: String,
@@ -147,60 +600,69 @@ enum InternalJsonValue {
     },
 }
 
+/// Mirrors the three kinds of number which a [`serde_json::Number`] can hold,
+/// plus the literals of any length it holds with the `arbitrary_precision`
+/// feature.
+#[derive(Clone)]
+enum InternalJsonNumber {
+    PosInt {
+        inner: u64,
+    },
+    NegInt {
+        inner: i64,
+    },
+    Float {
+        inner: f64,
+    },
+    #[cfg(feature = "arbitrary_precision")]
+    Decimal {
+        inner: String,
+    },
+}
+
+#[cfg(not(feature = "arbitrary_precision"))]
 make_mutator! {
-    name: InternalJsonValueMutator,
-    recursive: true,
+    name: InternalJsonNumberMutator,
     default: true,
-    type: enum InternalJsonValue {
-        Null,
-        Bool {
-            #[field_mutator(BoolMutator)]
-            inner: bool
-        },
-        Number {
+    type: enum InternalJsonNumber {
+        PosInt {
             #[field_mutator(U64Mutator)]
             inner: u64
         },
-        String {
-            #[field_mutator(StringMutator = {string_mutator()})]
-            inner: String
+        NegInt {
+            #[field_mutator(I64Mutator)]
+            inner: i64
         },
-        Array {
-            #[field_mutator(
-                VecMutator<
-                    InternalJsonValue,
-                    RecurToMutator<InternalJsonValueMutator>
-                > = {
-                    VecMutator::new(self_.into(), 0..=usize::MAX)
-                }
-            )]
-            inner: Vec<InternalJsonValue>,
-        },
-        Object {
-            #[field_mutator(
-                VecMutator<
-                    (String, InternalJsonValue),
-                    TupleMutatorWrapper<
-                        Tuple2Mutator<StringMutator, RecurToMutator<InternalJsonValueMutator>>,
-                        Tuple2<String, InternalJsonValue>
-                    >
-                > = {
-                    VecMutator::new(
-                        TupleMutatorWrapper::new(
-                            Tuple2Mutator::new(
-                                string_mutator(),
-                                self_.into()
-                            )
-                        ),
-                        0..=usize::MAX
-                    )
-                }
-            )]
-            inner: Vec<(String, InternalJsonValue)>,
+        Float {
+            #[field_mutator(FiniteF64Mutator = {finite_f64_mutator()})]
+            inner: f64
         },
     }
 }
 
+#[cfg(feature = "arbitrary_precision")]
+make_mutator! {
+    name: InternalJsonNumberMutator,
+    default: true,
+    type: enum InternalJsonNumber {
+        PosInt {
+            #[field_mutator(U64Mutator)]
+            inner: u64
+        },
+        NegInt {
+            #[field_mutator(I64Mutator)]
+            inner: i64
+        },
+        Float {
+            #[field_mutator(FiniteF64Mutator = {finite_f64_mutator()})]
+            inner: f64
+        },
+        Decimal {
+            #[field_mutator(DecimalLiteralMutator = {decimal::decimal_literal_mutator()})]
+            inner: String
+        },
+    }
+}
 
 #[cfg(test)]
 #[test]
@@ -221,3 +683,1223 @@ fn check_validity() {
     .launch();
     assert!(!result.found_test_failure)
 }
+
+#[cfg(test)]
+#[test]
+fn check_lossless_conversion() {
+    use fuzzcheck::Mutator;
+    use serde_json::json;
+
+    let round_trip = |value: &Value| {
+        assert_eq!(
+            &map_internal_jv_to_serde(map_serde_json_to_internal(value.clone())),
+            value
+        );
+    };
+
+    let hand_picked = [
+        json!(null),
+        json!(true),
+        json!(0),
+        json!(-1),
+        json!(u64::MAX),
+        json!(i64::MIN),
+        json!(-0.0),
+        json!(0.1),
+        json!(f64::MAX),
+        json!(f64::MIN_POSITIVE),
+        json!(1e-320),
+        json!(""),
+        json!("\"\\\u{0}\u{1f600}"),
+        json!([]),
+        json!({}),
+        json!([1, -1, 1.5, [{"a": [null]}]]),
+        json!({"a": {"b": [-9007199254740993i64, 9007199254740993u64, 2.5e-8]}, "": false}),
+    ];
+    for value in &hand_picked {
+        round_trip(value);
+    }
+
+    let mutator = json_value_mutator();
+    for _ in 0..10_000 {
+        let (value, _) = mutator.random_arbitrary(256.0);
+        round_trip(&value);
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn check_config_limits() {
+    use fuzzcheck::Mutator;
+
+    fn depth(value: &Value) -> usize {
+        match value {
+            Value::Array(array) => 1 + array.iter().map(depth).max().unwrap_or(0),
+            Value::Object(object) => 1 + object.values().map(depth).max().unwrap_or(0),
+            _ => 0,
+        }
+    }
+
+    fn check(value: &Value) {
+        assert!(depth(value) <= 3);
+        match value {
+            Value::String(string) => assert!(string.chars().count() <= 5),
+            Value::Array(array) => {
+                assert!(array.len() <= 4);
+                array.iter().for_each(check);
+            }
+            Value::Object(object) => {
+                assert!(object.len() <= 2);
+                for (key, value) in object {
+                    assert!(key.chars().count() <= 5);
+                    check(value);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mutator = json_value_mutator_with_config(
+        JsonValueMutatorConfig::new()
+            .max_depth(3)
+            .max_array_len(4)
+            .max_object_len(2)
+            .max_string_len(5),
+    );
+    for _ in 0..1_000 {
+        let (mut value, _) = mutator.random_arbitrary(256.0);
+        let mut cache = mutator.validate_value(&value).unwrap();
+        check(&value);
+        for _ in 0..100 {
+            mutator.random_mutate(&mut value, &mut cache, 256.0);
+            check(&value);
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn check_infinite_complexity() {
+    use fuzzcheck::Mutator;
+
+    let mutator = json_value_mutator();
+    for _ in 0..100 {
+        let (mut value, cplx) = mutator.random_arbitrary(f64::INFINITY);
+        assert!(cplx.is_finite());
+        let mut cache = mutator.validate_value(&value).unwrap();
+        for _ in 0..10 {
+            let (_, cplx) = mutator.random_mutate(&mut value, &mut cache, f64::INFINITY);
+            assert!(cplx.is_finite());
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn check_allowed_kinds() {
+    use fuzzcheck::Mutator;
+
+    let check = |value: &Value| {
+        let object = value.as_object().unwrap();
+        assert!(object.values().all(|v| v.is_string() || v.is_number()));
+    };
+
+    let mutator = json_value_mutator_with_config(
+        JsonValueMutatorConfig::new()
+            .root_kinds(ValueKinds::OBJECT)
+            .object_member_kinds(ValueKinds::STRING | ValueKinds::NUMBER),
+    );
+    for _ in 0..1_000 {
+        let (mut value, _) = mutator.random_arbitrary(256.0);
+        let mut cache = mutator.validate_value(&value).unwrap();
+        check(&value);
+        for _ in 0..100 {
+            mutator.random_mutate(&mut value, &mut cache, 256.0);
+            check(&value);
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn check_key_dictionary() {
+    use fuzzcheck::Mutator;
+
+    let keys = ["id", "type", "name"];
+    let mutator = json_value_mutator_with_config(
+        JsonValueMutatorConfig::new()
+            .key_dictionary(keys)
+            .arbitrary_key_probability(0.0),
+    );
+    for _ in 0..1_000 {
+        let (value, _) = mutator.random_arbitrary(256.0);
+        let mut stack = vec![&value];
+        while let Some(value) = stack.pop() {
+            match value {
+                Value::Array(array) => stack.extend(array),
+                Value::Object(object) => {
+                    assert!(object.keys().all(|key| keys.contains(&key.as_str())));
+                    stack.extend(object.values());
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn check_unicode_strings() {
+    use fuzzcheck::Mutator;
+
+    type CharacterClass = fn(char) -> bool;
+    let classes: [(&str, CharacterClass); 6] = [
+        ("control", |c| c.is_control()),
+        ("combining", |c| ('\u{300}'..='\u{36f}').contains(&c)),
+        ("right-to-left", |c| {
+            ('\u{590}'..='\u{6ff}').contains(&c) || ['\u{200f}', '\u{202e}'].contains(&c)
+        }),
+        ("zero-width joiner", |c| c == '\u{200d}'),
+        ("emoji", |c| ('\u{1f000}'..='\u{1faff}').contains(&c)),
+        ("astral", |c| c > '\u{ffff}'),
+    ];
+    let mut keys = String::new();
+    let mut strings = String::new();
+    let mutator =
+        json_value_mutator_with_config(JsonValueMutatorConfig::new().interesting_values(false));
+    for _ in 0..1_000 {
+        let (value, _) = mutator.random_arbitrary(256.0);
+        let mut stack = vec![&value];
+        while let Some(value) = stack.pop() {
+            match value {
+                Value::String(string) => strings.push_str(string),
+                Value::Array(array) => stack.extend(array),
+                Value::Object(object) => {
+                    keys.extend(object.keys().map(String::as_str));
+                    stack.extend(object.values());
+                }
+                _ => {}
+            }
+        }
+    }
+    for (name, class) in classes {
+        assert!(
+            keys.chars().any(class),
+            "no key contains a {name} character"
+        );
+        assert!(
+            strings.chars().any(class),
+            "no string contains a {name} character"
+        );
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn check_structural_mutations() {
+    use serde_json::json;
+
+    let mutator = InternalJsonValueMutator::new(JsonValueMutatorConfig::default());
+    let original = json!([5, {"a": "true"}]);
+    let mut seen = Vec::new();
+    for _ in 0..10_000 {
+        let mut value = map_serde_json_to_internal(original.clone());
+        if mutator.mutate_structure(&mut value, &mut Default::default()) {
+            seen.push(map_internal_jv_to_serde(value));
+        }
+    }
+    for expected in [
+        // copy
+        json!([5, 5]),
+        json!([{"a": "true"}, {"a": "true"}]),
+        // swap
+        json!([{"a": "true"}, 5]),
+        // wrap
+        json!([[5], {"a": "true"}]),
+        // unwrap
+        json!([5, "true"]),
+        // retype
+        json!(["5", {"a": "true"}]),
+        json!([5, {"a": true}]),
+    ] {
+        assert!(seen.contains(&expected), "{expected} was never generated");
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn check_crossover() {
+    use fuzzcheck::subvalue_provider::{CrossoverSubValueProvider, Generation};
+    use fuzzcheck::SubValueProviderId;
+    use serde_json::json;
+
+    let mutator = InternalJsonValueMutator::new(JsonValueMutatorConfig::default());
+    let donor = map_serde_json_to_internal(json!({"fragment": {"secret": [42]}}));
+    let cache = mutator.validate_value(&donor).unwrap();
+    let provider = CrossoverSubValueProvider::new(
+        SubValueProviderId {
+            idx: 0,
+            generation: Generation(0),
+        },
+        &donor,
+        &cache,
+        &mutator,
+    );
+
+    let original = map_serde_json_to_internal(json!({"a": [1], "b": {}}));
+    let original_cache = mutator.validate_value(&original).unwrap();
+    // the same step is used for every mutation, as it would be by the fuzzer
+    let mut step = mutator.default_mutation_step(&original, &original_cache);
+    let mut seen = Vec::new();
+    for _ in 0..10_000 {
+        let (mut value, mut cache) = (original.clone(), original_cache);
+        mutator.ordered_mutate(&mut value, &mut cache, &mut step, &provider, 256.0);
+        seen.push(map_internal_jv_to_serde(value));
+    }
+    for expected in [
+        // an element grafted into an array
+        json!({"a": [1, {"secret": [42]}], "b": {}}),
+        json!({"a": [[42], 1], "b": {}}),
+        // a member grafted into an object
+        json!({"a": [1], "b": {"secret": [42]}}),
+        // a value replaced
+        json!({"a": [1], "b": [42]}),
+    ] {
+        assert!(seen.contains(&expected), "{expected} was never generated");
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn check_complexity() {
+    use serde_json::json;
+
+    let serialized_len = |value: &Value| serde_json::to_string(value).unwrap().len() as f64;
+    let mutator = json_value_mutator();
+    let awkward = [
+        json!(""),
+        json!("\"\\/\u{8}\u{c}\n\r\t\0\u{1f}\u{7f}é\u{10ffff}"),
+        json!(-0.0),
+        json!(1e-7),
+        json!(f64::MAX),
+        json!(i64::MIN),
+        json!(u64::MAX),
+        json!([[], {}, [null, true, false]]),
+        json!({"\n": {"": 1.5}, "b": [0]}),
+    ];
+    for value in awkward {
+        let cache = mutator.validate_value(&value).unwrap();
+        assert_eq!(
+            mutator.complexity(&value, &cache),
+            serialized_len(&value),
+            "{value}"
+        );
+    }
+    for _ in 0..1_000 {
+        let (mut value, cplx) = mutator.random_arbitrary(256.0);
+        assert_eq!(cplx, serialized_len(&value));
+        let mut cache = mutator.validate_value(&value).unwrap();
+        for step in 0..10 {
+            let original = value.clone();
+            let (token, cplx) = mutator.random_mutate(&mut value, &mut cache, 256.0);
+            assert_eq!(cplx, serialized_len(&value));
+            assert_eq!(mutator.complexity(&value, &cache), cplx);
+            if step % 2 == 0 {
+                mutator.unmutate(&mut value, &mut cache, token);
+                assert_eq!(value, original);
+                assert_eq!(mutator.complexity(&value, &cache), serialized_len(&value));
+            }
+        }
+    }
+
+    let mutator = json_value_mutator_with_config(
+        JsonValueMutatorConfig::new().complexity_model(ComplexityModel::NodeCount),
+    );
+    let value = json!({"a long key": [1, "a long string", {}], "b": null});
+    let cache = mutator.validate_value(&value).unwrap();
+    assert_eq!(mutator.complexity(&value, &cache), 6.0);
+    for _ in 0..1_000 {
+        let (mut value, _) = mutator.random_arbitrary(256.0);
+        let mut cache = mutator.validate_value(&value).unwrap();
+        for _ in 0..10 {
+            let (_, cplx) = mutator.random_mutate(&mut value, &mut cache, 256.0);
+            let recomputed = mutator.validate_value(&value).unwrap();
+            assert_eq!(cplx, mutator.complexity(&value, &recomputed));
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn check_json_string() {
+    use std::collections::HashSet;
+
+    use fuzzcheck::Mutator;
+
+    let mutator = json_string_mutator();
+    for string in [
+        r#"{"b":1,"a":2,"b":[3,{"b":null,"b":true}]}"#,
+        "[-0.0,1e-7,\"\\n\"]",
+    ] {
+        let internal = serde_json::from_str::<InternalJsonValue>(string).unwrap();
+        assert_eq!(serde_json::to_string(&internal).unwrap(), string);
+        let cache = mutator.validate_value(&string.to_owned()).unwrap();
+        assert_eq!(
+            mutator.complexity(&string.to_owned(), &cache),
+            string.len() as f64
+        );
+    }
+    assert!(mutator.validate_value(&"{".to_owned()).is_none());
+
+    let mutator = json_string_mutator_with_config(
+        JsonValueMutatorConfig::new()
+            .max_depth(1)
+            .root_kinds(ValueKinds::OBJECT)
+            .key_dictionary(["a", "b"])
+            .arbitrary_key_probability(0.0),
+    );
+    let mut duplicates = 0;
+    for _ in 0..1_000 {
+        let (mut string, _) = mutator.random_arbitrary(256.0);
+        let mut cache = mutator.validate_value(&string).unwrap();
+        for _ in 0..10 {
+            let (_, cplx) = mutator.random_mutate(&mut string, &mut cache, 256.0);
+            assert_eq!(cplx, string.len() as f64);
+            let InternalJsonValue::Object { inner } = serde_json::from_str(&string).unwrap() else {
+                panic!("{string} is not an object");
+            };
+            let value = serde_json::from_str::<Value>(&string).unwrap();
+            if value.as_object().unwrap().len() < inner.len() {
+                duplicates += 1;
+            }
+        }
+    }
+    assert!(duplicates > 0);
+
+    // the other mutators never repeat a key, even with so few to pick from
+    let mutator = InternalJsonValueMutator::new(
+        JsonValueMutatorConfig::new()
+            .root_kinds(ValueKinds::OBJECT)
+            .key_dictionary(["a", "b"])
+            .arbitrary_key_probability(0.0),
+    );
+    let has_unique_keys = |value: &InternalJsonValue| {
+        let mut stack = vec![value];
+        while let Some(value) = stack.pop() {
+            match value {
+                InternalJsonValue::Array { inner } => stack.extend(inner),
+                InternalJsonValue::Object { inner } => {
+                    let keys = inner.iter().map(|(key, _)| key).collect::<HashSet<_>>();
+                    if keys.len() < inner.len() {
+                        return false;
+                    }
+                    stack.extend(inner.iter().map(|(_, value)| value));
+                }
+                _ => {}
+            }
+        }
+        true
+    };
+    for _ in 0..1_000 {
+        let (mut value, _) = mutator.random_arbitrary(256.0);
+        assert!(has_unique_keys(&value));
+        let mut cache = mutator.validate_value(&value).unwrap();
+        for _ in 0..10 {
+            mutator.random_mutate(&mut value, &mut cache, 256.0);
+            assert!(has_unique_keys(&value));
+        }
+    }
+    let duplicate = serde_json::from_str(r#"{"a":1,"b":{"a":2,"a":3}}"#).unwrap();
+    assert!(mutator.validate_value(&duplicate).is_none());
+}
+
+#[cfg(all(test, feature = "arbitrary_precision"))]
+#[test]
+fn check_decimal_literals() {
+    use fuzzcheck::Mutator;
+
+    for literal in [
+        "123456789012345678901234567890.000000000000000000001",
+        "-98765432109876543210",
+        "1e+400",
+        "-0",
+        "1.50",
+        "2.5e-3",
+        "7e+0",
+    ] {
+        let value: Value = serde_json::from_str(literal).unwrap();
+        let internal = map_serde_json_to_internal(value.clone());
+        assert_eq!(map_internal_jv_to_serde(internal), value);
+        assert_eq!(value.to_string(), literal);
+    }
+
+    let mutator = json_value_mutator();
+    let mut cache = mutator.validate_value(&Value::Null).unwrap();
+    let mut value = Value::Null;
+    let mut longest = 0;
+    for _ in 0..10_000 {
+        mutator.random_mutate(&mut value, &mut cache, 1024.0);
+        for_each_number(&value, &mut |number| {
+            let literal = number.to_string();
+            longest = longest.max(literal.len());
+            let reparsed: Value = serde_json::from_str(&literal).unwrap();
+            assert_eq!(reparsed, Value::Number(number.clone()));
+        });
+    }
+    assert!(
+        longest > 20,
+        "the longest number generated has {longest} characters"
+    );
+
+    fn for_each_number(value: &Value, f: &mut impl FnMut(&serde_json::Number)) {
+        match value {
+            Value::Number(number) => f(number),
+            Value::Array(array) => array.iter().for_each(|value| for_each_number(value, f)),
+            Value::Object(object) => object.values().for_each(|value| for_each_number(value, f)),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(all(test, feature = "preserve_order"))]
+#[test]
+fn check_member_order() {
+    use fuzzcheck::Mutator;
+    use serde_json::json;
+
+    fn keys(value: &Value, keys_so_far: &mut Vec<String>) {
+        match value {
+            Value::Array(array) => array.iter().for_each(|value| keys(value, keys_so_far)),
+            Value::Object(object) => {
+                for (key, value) in object {
+                    keys_so_far.push(key.clone());
+                    keys(value, keys_so_far);
+                }
+            }
+            _ => {}
+        }
+    }
+    let keys = |value: &Value| {
+        let mut keys_so_far = Vec::new();
+        keys(value, &mut keys_so_far);
+        keys_so_far
+    };
+
+    /// Serializes `value`, writing the members of objects in the order they
+    /// are iterated in.
+    fn serialize(value: &Value) -> String {
+        match value {
+            Value::Array(array) => {
+                let elements = array.iter().map(serialize).collect::<Vec<_>>();
+                format!("[{}]", elements.join(","))
+            }
+            Value::Object(object) => {
+                let members = object
+                    .iter()
+                    .map(|(key, value)| {
+                        format!("{}:{}", Value::from(key.as_str()), serialize(value))
+                    })
+                    .collect::<Vec<_>>();
+                format!("{{{}}}", members.join(","))
+            }
+            _ => value.to_string(),
+        }
+    }
+
+    let mutator = json_value_mutator();
+    let original = json!({"z": 1, "a": {"y": [{"x": null, "b": true}], "c": 2}, "m": {}});
+    assert_eq!(
+        serde_json::to_string(&original).unwrap(),
+        r#"{"z":1,"a":{"y":[{"x":null,"b":true}],"c":2},"m":{}}"#
+    );
+    let mut value = original.clone();
+    let mut cache = mutator.validate_value(&value).unwrap();
+    for _ in 0..1_000 {
+        let (token, _) = mutator.random_mutate(&mut value, &mut cache, 256.0);
+        assert_eq!(serde_json::to_string(&value).unwrap(), serialize(&value));
+        // the members which are neither added nor removed keep their order
+        if let Value::Object(object) = &value {
+            let kept = ["z", "a", "m"]
+                .into_iter()
+                .filter(|key| object.contains_key(*key))
+                .collect::<Vec<_>>();
+            let order = object
+                .keys()
+                .filter(|key| kept.contains(&key.as_str()))
+                .collect::<Vec<_>>();
+            assert_eq!(order, kept);
+        }
+        mutator.unmutate(&mut value, &mut cache, token);
+        assert_eq!(keys(&value), ["z", "a", "y", "x", "b", "c", "m"]);
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn check_typed() {
+    use fuzzcheck::Mutator;
+    use serde::Deserialize;
+
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Request {
+        user: String,
+        limit: Option<u32>,
+        tags: Vec<String>,
+        action: Action,
+        flags: (bool, u8),
+    }
+
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    enum Action {
+        Read,
+        Write { path: String, append: bool },
+        Delete(u64),
+    }
+
+    let examples = [
+        Request {
+            user: "alice".to_string(),
+            limit: None,
+            tags: vec![],
+            action: Action::Read,
+            flags: (false, 0),
+        },
+        Request {
+            user: "bob".to_string(),
+            limit: Some(10),
+            tags: vec!["new".to_string()],
+            action: Action::Write {
+                path: "/tmp".to_string(),
+                append: true,
+            },
+            flags: (true, 1),
+        },
+        Request {
+            user: "carol".to_string(),
+            limit: Some(0),
+            tags: vec![],
+            action: Action::Delete(7),
+            flags: (false, 255),
+        },
+    ];
+    // every value the mutators generate or mutate into round-trips through
+    // JSON, and is a valid value of the mutator
+    let check = |mutator: &JsonTypedMutator<Request>| {
+        let check = |request: &Request| {
+            let value = serde_json::to_value(request).unwrap();
+            let deserialized = Request::deserialize(&value).unwrap();
+            assert_eq!(serde_json::to_value(deserialized).unwrap(), value);
+            assert!(mutator.validate_value(request).is_some());
+        };
+        for _ in 0..100 {
+            let (mut request, _) = mutator.random_arbitrary(256.0);
+            check(&request);
+            let mut cache = mutator.validate_value(&request).unwrap();
+            for _ in 0..100 {
+                mutator.random_mutate(&mut request, &mut cache, 256.0);
+                check(&request);
+            }
+        }
+    };
+    check(&json_typed_mutator());
+    check(&json_typed_mutator_from_examples(examples));
+
+    // the simplest request, which is generated when nothing else deserializes,
+    // is within any maximum complexity
+    let simplest = r#"{"user":"","limit":null,"tags":[],"action":"Read","flags":[false,0]}"#;
+    let mutator = json_typed_mutator::<Request>();
+    assert!((0..1_000).any(|_| {
+        let (request, cplx) = mutator.random_arbitrary(0.0);
+        serde_json::to_string(&request).unwrap() == simplest && cplx == simplest.len() as f64
+    }));
+
+    // a type which arbitrary values never deserialize into is still
+    // generated (from its examples), and mutations of it terminate
+    #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(try_from = "String", into = "String")]
+    struct Password(String);
+
+    impl TryFrom<String> for Password {
+        type Error = &'static str;
+
+        fn try_from(password: String) -> Result<Self, Self::Error> {
+            match password.as_str() {
+                "open sesame" | "open sesame, and be quick about it" => Ok(Password(password)),
+                _ => Err("wrong password"),
+            }
+        }
+    }
+
+    impl From<Password> for String {
+        fn from(password: Password) -> Self {
+            password.0
+        }
+    }
+
+    // its simplest value doesn't deserialize
+    assert!(std::panic::catch_unwind(json_typed_mutator::<Password>).is_err());
+
+    let password = Password("open sesame".to_string());
+    let long_password = Password("open sesame, and be quick about it".to_string());
+    let mutator = json_typed_mutator_from_examples([password.clone(), long_password.clone()]);
+    let mut step = mutator.default_arbitrary_step();
+    for _ in 0..100 {
+        // the examples are only generated if they are within the maximum
+        // complexity, unless none of them are
+        for max_cplx in [0.0, 16.0] {
+            let (value, _) = mutator.random_arbitrary(max_cplx);
+            assert_eq!(value, password);
+            let (value, _) = mutator.ordered_arbitrary(&mut step, max_cplx).unwrap();
+            assert_eq!(value, password);
+        }
+        let (mut value, _) = mutator.random_arbitrary(256.0);
+        assert!(value == password || value == long_password);
+        let mut cache = mutator.validate_value(&value).unwrap();
+        mutator.random_mutate(&mut value, &mut cache, 256.0);
+        assert!(value == password || value == long_password);
+    }
+    assert!(mutator
+        .validate_value(&Password("abracadabra".to_string()))
+        .is_none());
+}
+
+#[cfg(test)]
+#[test]
+fn check_examples() {
+    use fuzzcheck::Mutator;
+    use serde_json::json;
+
+    fn has_shape(value: &Value) -> bool {
+        let Some(object) = value.as_object() else {
+            return false;
+        };
+        object.contains_key("id")
+            && object.contains_key("tags")
+            && object.iter().all(|(key, value)| match key.as_str() {
+                "id" => value.as_f64().is_some_and(|id| id.fract() == 0.0),
+                "name" => value.is_string(),
+                "tags" => value
+                    .as_array()
+                    .is_some_and(|tags| tags.iter().all(Value::is_string)),
+                "parent" => value.is_number(),
+                _ => false,
+            })
+    }
+
+    let examples = [
+        json!({"id": 1, "name": "a", "tags": ["x"]}),
+        json!({"id": 2, "tags": [], "parent": 1.5}),
+    ];
+    let mutator = json_example_mutator_with_config(
+        examples.clone(),
+        JsonValueMutatorConfig::new().deviation_probability(0.0),
+    );
+    for _ in 0..1_000 {
+        let (mut value, _) = mutator.random_arbitrary(256.0);
+        assert!(has_shape(&value), "{value}");
+        let mut cache = mutator.validate_value(&value).unwrap();
+        mutator.random_mutate(&mut value, &mut cache, 256.0);
+        assert!(has_shape(&value), "{value}");
+    }
+
+    // when it always deviates from the shape, the mutator generates values of
+    // any kind
+    let mutator = json_example_mutator_with_config(
+        examples,
+        JsonValueMutatorConfig::new().deviation_probability(1.0),
+    );
+    assert!((0..1_000).any(|_| !has_shape(&mutator.random_arbitrary(256.0).0)));
+}
+
+#[cfg(test)]
+#[test]
+fn check_schema() {
+    use fuzzcheck::Mutator;
+    use serde_json::json;
+
+    fn check_node(node: &Value) {
+        let node = node.as_object().unwrap();
+        assert!(node.keys().all(|key| key == "value" || key == "children"));
+        // JSON Schema considers floats without a fractional part to be integers
+        let value = node["value"].as_f64().unwrap();
+        assert!(value.fract() == 0.0 && (-5.0..5.0).contains(&value));
+        if let Some(children) = node.get("children") {
+            let children = children.as_array().unwrap();
+            assert!(children.len() <= 3);
+            children.iter().for_each(check_node);
+        }
+    }
+
+    fn check(value: &Value) {
+        let object = value.as_object().unwrap();
+        for key in ["kind", "version", "name", "tree"] {
+            assert!(object.contains_key(key));
+        }
+        for (key, value) in object {
+            match key.as_str() {
+                // `3.0` and `3e+0` are as valid as `3`
+                "kind" => {
+                    assert!([json!("a"), json!("b")].contains(value) || value.as_f64() == Some(3.0))
+                }
+                "version" => assert_eq!(value.as_f64(), Some(2.0)),
+                "name" => assert!((2..=4).contains(&value.as_str().unwrap().chars().count())),
+                "ratio" => assert!((0.0..=1.0).contains(&value.as_f64().unwrap())),
+                "tags" => {
+                    let tags = value.as_array().unwrap();
+                    assert!((1..=3).contains(&tags.len()));
+                    assert!(tags.iter().all(Value::is_string));
+                }
+                "id" => assert!(
+                    value.as_f64().is_some_and(|id| id.fract() == 0.0)
+                        || value.as_str().is_some_and(|id| id.chars().count() <= 3)
+                ),
+                "extra" => assert!(value.is_null() || value.is_boolean()),
+                "tree" => check_node(value),
+                _ => panic!("unexpected member {key}"),
+            }
+        }
+    }
+
+    let mutator = json_schema_mutator(&json!({
+        "$defs": {
+            "node": {
+                "type": "object",
+                "properties": {
+                    "value": { "type": "integer", "minimum": -5, "exclusiveMaximum": 5 },
+                    "children": { "type": "array", "items": { "$ref": "#/$defs/node" }, "maxItems": 3 }
+                },
+                "required": ["value"],
+                "additionalProperties": false
+            }
+        },
+        "type": "object",
+        "properties": {
+            "kind": { "enum": ["a", "b", 3] },
+            "version": { "const": 2 },
+            "name": { "type": "string", "minLength": 2, "maxLength": 4 },
+            "ratio": { "type": "number", "minimum": 0, "maximum": 1 },
+            "tags": { "type": "array", "items": { "type": "string" }, "minItems": 1, "maxItems": 3 },
+            "id": { "oneOf": [{ "type": "integer" }, { "type": "string", "maxLength": 3 }] },
+            "extra": { "anyOf": [{ "type": "null" }, { "type": "boolean" }] },
+            "tree": { "$ref": "#/$defs/node" }
+        },
+        "required": ["kind", "version", "name", "tree"],
+        "additionalProperties": false
+    }))
+    .unwrap();
+    for _ in 0..1_000 {
+        let (mut value, _) = mutator.random_arbitrary(256.0);
+        let mut cache = mutator.validate_value(&value).unwrap();
+        check(&value);
+        for _ in 0..100 {
+            mutator.random_mutate(&mut value, &mut cache, 256.0);
+            check(&value);
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn check_schema_siblings() {
+    use fuzzcheck::Mutator;
+    use serde_json::json;
+
+    fn integer(value: &Value) -> Option<f64> {
+        value.as_f64().filter(|value| value.fract() == 0.0)
+    }
+    let check = |schema: Value, check: &dyn Fn(&Value) -> bool| {
+        let mutator = json_schema_mutator(&schema).unwrap();
+        for _ in 0..200 {
+            let (mut value, _) = mutator.random_arbitrary(256.0);
+            let mut cache = mutator.validate_value(&value).unwrap();
+            assert!(check(&value), "{value} doesn't match {schema}");
+            for _ in 0..20 {
+                mutator.random_mutate(&mut value, &mut cache, 256.0);
+                assert!(check(&value), "{value} doesn't match {schema}");
+            }
+        }
+    };
+
+    // the siblings are stricter than the branches
+    check(
+        json!({ "type": "integer", "minimum": 0, "maximum": 10, "oneOf": [{ "minimum": -10, "maximum": 100 }] }),
+        &|value| integer(value).is_some_and(|value| (0.0..=10.0).contains(&value)),
+    );
+    check(
+        json!({ "type": "string", "maxLength": 3, "anyOf": [{ "type": ["number", "string"], "maxLength": 10 }] }),
+        &|value| {
+            value
+                .as_str()
+                .is_some_and(|value| value.chars().count() <= 3)
+        },
+    );
+    check(
+        json!({ "type": "number", "anyOf": [{ "type": "integer", "exclusiveMaximum": 0 }, { "type": ["string", "null"] }] }),
+        &|value| integer(value).is_some_and(|value| value < 0.0),
+    );
+    check(
+        json!({ "enum": [1, 2, 3], "anyOf": [{ "const": 2 }, { "enum": [3, 4] }] }),
+        &|value| *value == json!(2) || *value == json!(3),
+    );
+    check(
+        json!({
+            "type": "object",
+            "properties": { "a": { "type": "integer", "maximum": 0 } },
+            "required": ["a"],
+            "oneOf": [{ "properties": { "a": { "minimum": -3 } } }]
+        }),
+        &|value| integer(&value["a"]).is_some_and(|a| (-3.0..=0.0).contains(&a)),
+    );
+
+    // the keywords next to a `$ref` apply too, even inside the schema it
+    // refers to
+    check(
+        json!({ "$defs": { "n": { "type": "integer" } }, "$ref": "#/$defs/n", "minimum": 5 }),
+        &|value| integer(value).is_some_and(|value| value >= 5.0),
+    );
+    fn is_node(value: &Value) -> bool {
+        integer(&value["value"]).is_some()
+            && value
+                .get("children")
+                .is_none_or(|children| children.as_array().unwrap().iter().all(is_node))
+    }
+    check(
+        json!({
+            "$defs": {
+                "node": {
+                    "type": "object",
+                    "properties": {
+                        "value": { "type": "integer" },
+                        "children": {
+                            "type": "array",
+                            "items": { "$ref": "#/$defs/node", "required": ["value"] },
+                            "maxItems": 2
+                        }
+                    },
+                    "additionalProperties": false
+                }
+            },
+            "$ref": "#/$defs/node",
+            "required": ["value"]
+        }),
+        &is_node,
+    );
+
+    // `const` and `enum` both apply
+    check(json!({ "const": 2, "enum": [1, 2] }), &|value| {
+        *value == json!(2)
+    });
+    // lengths written as floats
+    check(
+        json!({ "type": "string", "minLength": 2.0, "maxLength": 3.0 }),
+        &|value| {
+            value
+                .as_str()
+                .is_some_and(|value| (2..=3).contains(&value.chars().count()))
+        },
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn check_one_of() {
+    use fuzzcheck::Mutator;
+    use serde_json::json;
+
+    // most integers and non-negative numbers match both branches
+    let mutator = json_schema_mutator(&json!({
+        "oneOf": [{ "type": "integer" }, { "type": "number", "minimum": 0, "maximum": 1000 }]
+    }))
+    .unwrap();
+    let matches = |value: &Value| {
+        value
+            .as_f64()
+            .is_some_and(|value| (value.fract() == 0.0) != (0.0..=1000.0).contains(&value))
+    };
+    for _ in 0..1_000 {
+        let (mut value, _) = mutator.random_arbitrary(256.0);
+        let mut cache = mutator.validate_value(&value).unwrap();
+        assert!(matches(&value), "{value}");
+        for _ in 0..20 {
+            mutator.random_mutate(&mut value, &mut cache, 256.0);
+            assert!(matches(&value), "{value}");
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn check_schema_errors() {
+    use serde_json::json;
+
+    let error = |schema: Value| json_schema_mutator(&schema).err().unwrap();
+
+    assert_eq!(error(json!(false)), SchemaError::Unsatisfiable);
+    assert_eq!(
+        error(json!({ "oneOf": [{ "type": "null" }, { "enum": [null] }] })),
+        SchemaError::Unsatisfiable
+    );
+    assert_eq!(
+        error(json!({ "type": "integer", "minimum": 0.2, "maximum": 0.8 })),
+        SchemaError::Unsatisfiable
+    );
+    assert_eq!(
+        error(
+            json!({ "type": "object", "properties": { "a": { "$ref": "#" } }, "required": ["a"] })
+        ),
+        SchemaError::Unsatisfiable
+    );
+    assert_eq!(
+        error(json!({ "const": 5, "enum": [1, 2] })),
+        SchemaError::Unsatisfiable
+    );
+    assert_eq!(
+        error(json!({ "$ref": "other.json#/node" })),
+        SchemaError::UnresolvableReference("other.json#/node".to_owned())
+    );
+    assert_eq!(
+        error(json!({ "anyOf": [{ "type": "null" }], "oneOf": [{ "anyOf": [{}] }] })),
+        SchemaError::UnsupportedKeyword {
+            pointer: "/oneOf/0".to_owned(),
+            keyword: "anyOf".to_owned()
+        }
+    );
+    assert_eq!(
+        error(json!({
+            "properties": { "a": {} },
+            "additionalProperties": false,
+            "oneOf": [{ "properties": { "b": {} } }]
+        })),
+        SchemaError::UnsupportedKeyword {
+            pointer: "/oneOf/0".to_owned(),
+            keyword: "additionalProperties".to_owned()
+        }
+    );
+    assert_eq!(
+        error(json!({ "properties": { "a": { "minLength": -1 } } })),
+        SchemaError::InvalidKeyword {
+            pointer: "/properties/a".to_owned(),
+            keyword: "minLength".to_owned()
+        }
+    );
+    assert_eq!(
+        error(json!({ "minItems": 1.5 })),
+        SchemaError::InvalidKeyword {
+            pointer: "".to_owned(),
+            keyword: "minItems".to_owned()
+        }
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn check_near_miss() {
+    use fuzzcheck::Mutator;
+    use serde_json::json;
+
+    /// Lists the constraints of the schema below which `value` violates.
+    fn violations(value: &Value) -> Vec<(String, &'static str)> {
+        let object = match value.as_object() {
+            Some(object) => object,
+            None => return vec![(String::new(), "type")],
+        };
+        let mut violations = Vec::new();
+        for key in ["name", "port"] {
+            if !object.contains_key(key) {
+                violations.push((String::new(), "required"));
+            }
+        }
+        for (key, value) in object {
+            let pointer = format!("/{key}");
+            match key.as_str() {
+                "name" => match value.as_str().map(|name| name.chars().count()) {
+                    None => violations.push((pointer, "type")),
+                    Some(0) => violations.push((pointer, "minLength")),
+                    Some(9..) => violations.push((pointer, "maxLength")),
+                    _ => {}
+                },
+                "port" => match value.as_f64() {
+                    Some(port) if port.fract() == 0.0 => {
+                        if port < 1.0 {
+                            violations.push((pointer, "minimum"));
+                        } else if port > 65535.0 {
+                            violations.push((pointer, "maximum"));
+                        }
+                    }
+                    _ => violations.push((pointer, "type")),
+                },
+                "mode" => {
+                    if value != "a" && value != "b" {
+                        violations.push((pointer, "enum"));
+                    }
+                }
+                "tags" => match value.as_array() {
+                    None => violations.push((pointer, "type")),
+                    Some(tags) => {
+                        if tags.is_empty() {
+                            violations.push((pointer.clone(), "minItems"));
+                        } else if tags.len() > 3 {
+                            violations.push((pointer.clone(), "maxItems"));
+                        }
+                        for (idx, tag) in tags.iter().enumerate() {
+                            match tag.as_str() {
+                                None => violations.push((format!("{pointer}/{idx}"), "type")),
+                                Some(tag) if tag.chars().count() > 3 => {
+                                    violations.push((format!("{pointer}/{idx}"), "maxLength"))
+                                }
+                                _ => {}
+                            }
+                        }
+                    }
+                },
+                _ => violations.push((String::new(), "additionalProperties")),
+            }
+        }
+        violations
+    }
+
+    let check = |(value, violation): &(Value, SchemaViolation)| {
+        assert_eq!(
+            violations(value),
+            [(violation.pointer.clone(), violation.kind.keyword())],
+            "{value} should only have the violation: {violation}"
+        );
+    };
+
+    let mutator = json_schema_near_miss_mutator(&json!({
+        "type": "object",
+        "properties": {
+            "name": { "type": "string", "minLength": 1, "maxLength": 8 },
+            "port": { "type": "integer", "minimum": 1, "maximum": 65535 },
+            "mode": { "enum": ["a", "b"] },
+            "tags": {
+                "type": "array",
+                "items": { "type": "string", "maxLength": 3 },
+                "minItems": 1,
+                "maxItems": 3
+            }
+        },
+        "required": ["name", "port"],
+        "additionalProperties": false
+    }))
+    .unwrap();
+    for _ in 0..1_000 {
+        let (mut value, _) = mutator.random_arbitrary(256.0);
+        check(&value);
+        let mut cache = mutator.validate_value(&value).unwrap();
+        for _ in 0..100 {
+            mutator.random_mutate(&mut value, &mut cache, 256.0);
+            check(&value);
+        }
+    }
+
+    assert_eq!(
+        json_schema_near_miss_mutator(
+            &json!({ "anyOf": [{ "type": "null" }, { "type": "string" }] })
+        )
+        .err(),
+        Some(SchemaError::NothingToViolate)
+    );
+
+    // values generated at random (let alone simple ones) rarely have a member
+    // `a` which can be broken
+    let mutator =
+        json_schema_near_miss_mutator(&json!({ "properties": { "a": { "type": "integer" } } }))
+            .unwrap();
+    for max_cplx in [0.0, 1.0, 256.0] {
+        let ((value, violation), _) = mutator.random_arbitrary(max_cplx);
+        assert_eq!(violation.pointer, "/a");
+        assert!(value["a"].as_f64().is_none_or(|a| a.fract() != 0.0));
+    }
+
+    // a value of another type still satisfies the constraints for that type
+    let mutator = json_schema_near_miss_mutator(&json!({
+        "type": "object",
+        "properties": { "a": { "type": "string", "minimum": 5, "maxItems": 0 } },
+        "required": ["a"]
+    }))
+    .unwrap();
+    for _ in 0..1_000 {
+        let ((value, violation), _) = mutator.random_arbitrary(256.0);
+        if violation.pointer == "/a" {
+            assert!(value["a"].as_f64().is_none_or(|a| a >= 5.0), "{value}");
+            assert!(value["a"].as_array().is_none_or(Vec::is_empty), "{value}");
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn check_ndjson() {
+    use fuzzcheck::subvalue_provider::EmptySubValueProvider;
+    use fuzzcheck::Mutator;
+    use serde_json::json;
+
+    // every stream is made of the documents generated along with it, each on
+    // its own line, whether it was generated or mutated
+    let mutator = ndjson_value_mutator();
+    let check = |(stream, values): &(String, Vec<Value>)| {
+        let documents = stream
+            .split('\n')
+            .map(|line| line.strip_suffix('\r').unwrap_or(line))
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str::<Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(&documents, values, "{stream:?}");
+    };
+    for _ in 0..100 {
+        let (mut value, _) = mutator.random_arbitrary(256.0);
+        check(&value);
+        let mut cache = mutator.validate_value(&value).unwrap();
+        let mut step = mutator.default_mutation_step(&value, &cache);
+        // each mutation is undone after being checked, as it would be by the
+        // fuzzer
+        for _ in 0..100 {
+            let Some((token, _)) = mutator.ordered_mutate(
+                &mut value,
+                &mut cache,
+                &mut step,
+                &EmptySubValueProvider,
+                256.0,
+            ) else {
+                break;
+            };
+            check(&value);
+            mutator.unmutate(&mut value, &mut cache, token);
+        }
+    }
+
+    let records = [(json!(1), 1), (json!({"a": [2]}), 3), (json!("b"), 5)];
+    for (terminated, stream) in [
+        (true, "1\r\n{\"a\":[2]}\r\n\r\n\"b\"\n\t\r\n"),
+        (false, "1\r\n{\"a\":[2]}\r\n\r\n\"b\""),
+    ] {
+        assert_eq!(ndjson::write(&records, terminated), stream);
+        let values = records
+            .iter()
+            .map(|(value, _)| value.clone())
+            .collect::<Vec<_>>();
+        let (read, read_terminated) = ndjson::read(stream, &values).unwrap();
+        assert_eq!(
+            (ndjson::write(&read, read_terminated), read_terminated),
+            (stream.to_string(), terminated)
+        );
+        assert!(mutator
+            .validate_value(&(stream.to_string(), values))
+            .is_some());
+    }
+    // the documents don't match the stream, or aren't separated by one of the
+    // line breaks the mutator writes
+    for (stream, values) in [
+        ("1\n", vec![json!(2)]),
+        ("1\n2\n", vec![json!(1)]),
+        ("1 \n", vec![json!(1)]),
+        ("1\n\n\n2", vec![json!(1), json!(2)]),
+        ("[1, 2]\n", vec![json!([1, 2])]),
+    ] {
+        assert!(mutator
+            .validate_value(&(stream.to_string(), values))
+            .is_none());
+    }
+}
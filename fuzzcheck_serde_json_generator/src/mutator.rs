@@ -0,0 +1,1579 @@
+//! The mutator for [`InternalJsonValue`]. Unlike a mutator derived with
+//! `make_mutator!` it knows the depth and the [`Shape`] of every value it
+//! generates, which is what allows it to respect the limits in
+//! [`JsonValueMutatorConfig`] and the constraints of a JSON Schema.
+
+use std::any::{Any, TypeId};
+use std::ops::{Bound, RangeInclusive};
+
+use fuzzcheck::fastrand::Rng;
+use fuzzcheck::mutators::alternation::AlternationMutator;
+use fuzzcheck::mutators::character_classes::CharacterMutator;
+use fuzzcheck::mutators::map::MapMutator;
+use fuzzcheck::mutators::vector::VecMutator;
+use fuzzcheck::mutators::CrossoverStep;
+use fuzzcheck::{DefaultMutator, Mutator, SubValueProvider};
+
+use crate::dictionary::{interesting_values, Dictionary};
+use crate::shape::{integer_number, kind_of, Constraints, Shape, ShapeId, ShapeNode, ANY};
+use crate::{
+    map_internal_number_to_serde, map_serde_json_number_to_internal, map_serde_json_to_internal,
+    ComplexityModel, InternalJsonNumber, InternalJsonValue, JsonValueMutatorConfig, ValueKinds,
+};
+
+/// The string mutator measures complexity in bits, whereas the value mutator
+/// measures it in bytes.
+const BITS_PER_BYTE: f64 = 8.0;
+
+/// How many times a mutation is retried when it produces a value which is too
+/// complex or invalid (e.g. because a value inside a `oneOf` now matches
+/// several of its branches) before giving up and leaving the value unchanged.
+const MAX_MUTATION_ATTEMPTS: usize = 16;
+
+/// How many values are generated for a `oneOf`, or numbers for a range, before
+/// giving up on finding one which matches (and falling back to the simplest
+/// value which does).
+const MAX_GENERATION_ATTEMPTS: usize = 16;
+
+/// How many nodes of the shape are visited when looking for its simplest value
+/// (see [`InternalJsonValueMutator::simplest`]) or checking whether any value
+/// can be generated, which stops cycles of `anyOf` nodes.
+pub(crate) const MAX_VISITED_NODES: usize = 1024;
+
+/// The probability that a mutation moves values around or changes their kind
+/// (see [`InternalJsonValueMutator::mutate_structure`]) rather than mutating a
+/// single value.
+const STRUCTURAL_MUTATION_PROBABILITY: f64 = 0.25;
+
+/// The probability that a mutation grafts a value taken from another input
+/// (see [`InternalJsonValueMutator::graft`]) rather than mutating this one.
+const CROSSOVER_PROBABILITY: f64 = 0.1;
+
+/// The budget used instead of an infinite (or NaN) one, e.g. when the
+/// maximum complexity is `f64::INFINITY` and the configuration doesn't limit
+/// the length of arrays, objects and strings.
+const UNBOUNDED_BUDGET: f64 = 4096.0;
+
+/// The budget for the key of the object created when wrapping a value.
+const WRAPPER_KEY_BUDGET: f64 = 8.0;
+
+/// How many entries of the value dictionary are tried before giving up on
+/// finding one which can be placed at a given position.
+const MAX_DICTIONARY_ATTEMPTS: usize = 8;
+
+pub(crate) type BoundedStringMutator = impl Mutator<String>;
+
+/// The classes of characters which strings (both keys and values) are made
+/// of, each of which is picked as often as the others. Most of them are tiny
+/// compared to the rest of Unicode, but are where bugs in the normalization,
+/// escaping, rendering or truncation of strings tend to hide.
+const CHARACTER_CLASSES: [&[RangeInclusive<char>]; 8] = [
+    // printable ASCII, which includes the `"` and `\` that have to be escaped
+    &[' '..='~'],
+    // control characters, most of which have to be escaped as `\u00XX`
+    &['\0'..='\u{1f}', '\u{7f}'..='\u{9f}'],
+    // combining marks, which change the character before them
+    &[
+        '\u{300}'..='\u{36f}',
+        '\u{1ab0}'..='\u{1aff}',
+        '\u{1dc0}'..='\u{1dff}',
+        '\u{20d0}'..='\u{20ff}',
+        '\u{fe20}'..='\u{fe2f}',
+    ],
+    // right-to-left letters, and the marks, embeddings and isolates which
+    // change the direction of text
+    &[
+        '\u{590}'..='\u{5ff}',
+        '\u{600}'..='\u{6ff}',
+        '\u{61c}'..='\u{61c}',
+        '\u{200e}'..='\u{200f}',
+        '\u{202a}'..='\u{202e}',
+        '\u{2066}'..='\u{2069}',
+    ],
+    // invisible characters: zero-width spaces and joiners, the line and
+    // paragraph separators (which JavaScript doesn't allow unescaped in its
+    // string literals until ES2019), and the byte order mark
+    &[
+        '\u{200b}'..='\u{200d}',
+        '\u{2028}'..='\u{2029}',
+        '\u{2060}'..='\u{2064}',
+        '\u{feff}'..='\u{feff}',
+    ],
+    // emoji, along with the zero-width joiner, variation selectors, skin tone
+    // modifiers and tags which combine them into a single glyph
+    &[
+        '\u{200d}'..='\u{200d}',
+        '\u{2600}'..='\u{27bf}',
+        '\u{fe0e}'..='\u{fe0f}',
+        '\u{1f000}'..='\u{1faff}',
+        '\u{e0020}'..='\u{e007f}',
+    ],
+    // characters outside the Basic Multilingual Plane, which take four bytes
+    // in UTF-8 and a surrogate pair in UTF-16 (and in `\u` escapes), and
+    // noncharacters
+    &[
+        '\u{10000}'..='\u{10ffff}',
+        '\u{fdd0}'..='\u{fdef}',
+        '\u{fffe}'..='\u{ffff}',
+    ],
+    // the rest of Unicode
+    &['\u{a0}'..='\u{d7ff}', '\u{e000}'..='\u{fffd}'],
+];
+
+/// Replaces a budget which isn't finite by [`UNBOUNDED_BUDGET`], so that it
+/// can be turned into a length.
+fn finite_budget(budget: f64) -> f64 {
+    if budget.is_nan() || budget == f64::INFINITY {
+        UNBOUNDED_BUDGET
+    } else {
+        budget
+    }
+}
+
+/// Generates strings of at most `max_len` `char`s, split evenly between the
+/// [`CHARACTER_CLASSES`].
+fn bounded_string_mutator(max_len: usize) -> BoundedStringMutator {
+    MapMutator::new(
+        VecMutator::new(
+            AlternationMutator::new(
+                CHARACTER_CLASSES
+                    .iter()
+                    .map(|class| CharacterMutator::new(class.to_vec()))
+                    .collect(),
+                0.0,
+            ),
+            0..=max_len,
+        ),
+        |string: &String| Some(string.chars().collect()),
+        |chars| chars.iter().collect(),
+        |_, cplx| cplx,
+    )
+}
+
+pub(crate) struct InternalJsonValueMutator {
+    config: JsonValueMutatorConfig,
+    shape: Shape,
+    number_mutator: <InternalJsonNumber as DefaultMutator>::Mutator,
+    string_mutator: BoundedStringMutator,
+    key_dictionary: Option<Dictionary<String>>,
+    /// The entries of the value dictionary, along with their complexity.
+    value_dictionary: Option<Dictionary<(InternalJsonValue, f64)>>,
+    /// Whether objects may be given several members with the same key.
+    duplicate_keys: bool,
+    rng: Rng,
+}
+
+impl InternalJsonValueMutator {
+    pub(crate) fn new(config: JsonValueMutatorConfig) -> Self {
+        assert!(
+            !config.root_kinds.is_empty()
+                && !config.array_element_kinds.is_empty()
+                && !config.object_member_kinds.is_empty(),
+            "at least one kind of value must be allowed at every position"
+        );
+        let shape = Shape::from_config(&config);
+        let mutator = Self::with_shape(config, shape);
+        assert!(
+            mutator.simplest(mutator.shape.root(), 0).is_some(),
+            "the root can only be an array or an object, but the maximum depth is 0"
+        );
+        mutator
+    }
+
+    /// Creates a mutator generating values which match `shape` and respect the
+    /// limits in `config` (the kinds of value in `config` are ignored).
+    pub(crate) fn with_shape(config: JsonValueMutatorConfig, mut shape: Shape) -> Self {
+        shape.set_complexity_model(config.complexity_model);
+        let max_string_len = config.max_string_len;
+        Self {
+            shape,
+            number_mutator: InternalJsonNumber::default_mutator(),
+            string_mutator: bounded_string_mutator(max_string_len),
+            key_dictionary: Dictionary::new(
+                config
+                    .key_dictionary
+                    .iter()
+                    .filter(|(key, _)| key.chars().count() <= max_string_len)
+                    .cloned(),
+            ),
+            value_dictionary: Dictionary::new(
+                config
+                    .value_dictionary
+                    .iter()
+                    .cloned()
+                    .chain(if config.interesting_values {
+                        interesting_values()
+                    } else {
+                        Vec::new()
+                    })
+                    .map(|value| {
+                        let value = map_serde_json_to_internal(value);
+                        let cplx = config.complexity_model.of_internal(&value);
+                        ((value, cplx), 1.0)
+                    }),
+            ),
+            config,
+            duplicate_keys: false,
+            rng: Rng::new(),
+        }
+    }
+
+    /// Also mutates objects by adding a member with the same key as an
+    /// existing one. This is pointless when the values are converted to
+    /// [`serde_json::Value`]s, which can't hold duplicate keys.
+    pub(crate) fn with_duplicate_keys(mut self) -> Self {
+        self.duplicate_keys = true;
+        self
+    }
+
+    pub(crate) fn shape(&self) -> &Shape {
+        &self.shape
+    }
+
+    pub(crate) fn complexity_model(&self) -> ComplexityModel {
+        self.config.complexity_model
+    }
+
+    pub(crate) fn cplx(&self, value: &InternalJsonValue) -> f64 {
+        self.config.complexity_model.of_internal(value)
+    }
+
+    /// Whether the shape has expectations beyond the values it matches (see
+    /// [`ShapeNode::Usually`]) and `value` meets them.
+    fn is_expected(&self, value: &InternalJsonValue) -> bool {
+        self.shape.deviation_probability().is_some()
+            && self.shape.is_expected(self.shape.root(), value)
+    }
+
+    /// Whether `value`, a mutation of a value which [was
+    /// expected](Self::is_expected), is valid and may be kept. If the original
+    /// value was what the shape expects, `value` only stops being so once in a
+    /// while.
+    fn may_keep(&self, was_expected: bool, value: &InternalJsonValue) -> bool {
+        let root = self.shape.root();
+        self.is_valid(value)
+            && self
+                .shape
+                .deviation_probability()
+                .is_none_or(|probability| {
+                    self.shape.is_expected(root, value)
+                        || !was_expected
+                        || self.rng.f64() < probability
+                })
+    }
+
+    /// Returns `true` if there is a value matching the shape `id` which may be
+    /// placed inside `depth` arrays or objects.
+    pub(crate) fn may_generate(&self, id: ShapeId, depth: usize) -> bool {
+        self.may_generate_within(id, depth, &mut { MAX_VISITED_NODES })
+    }
+
+    fn may_generate_within(&self, id: ShapeId, depth: usize, visits: &mut usize) -> bool {
+        if *visits == 0 || !self.shape.min_complexity(id).is_finite() {
+            return false;
+        }
+        *visits -= 1;
+        match self.shape.node(id) {
+            ShapeNode::Constraints(constraints) => constraints
+                .kinds
+                .iter()
+                .any(|kind| self.may_generate_kind(constraints, kind, depth)),
+            ShapeNode::Enum { values, .. } => values
+                .iter()
+                .any(|value| self.is_within_limits(value, depth)),
+            ShapeNode::AnyOf(ids) | ShapeNode::OneOf(ids) => ids
+                .iter()
+                .any(|id| self.may_generate_within(*id, depth, visits)),
+            ShapeNode::Usually { expected, .. } => {
+                self.may_generate_within(*expected, depth, visits)
+                    || self.may_generate_within(ANY, depth, visits)
+            }
+        }
+    }
+
+    /// Returns `true` if the simplest value of the given kind which satisfies
+    /// `constraints` may be placed inside `depth` arrays or objects.
+    fn may_generate_kind(&self, constraints: &Constraints, kind: ValueKinds, depth: usize) -> bool {
+        self.shape
+            .kind_min_complexity(constraints, kind)
+            .is_finite()
+            && match kind {
+                ValueKinds::STRING => constraints.min_length <= self.config.max_string_len,
+                ValueKinds::ARRAY => {
+                    depth < self.config.max_depth
+                        && constraints.min_items <= self.config.max_array_len
+                }
+                ValueKinds::OBJECT => {
+                    depth < self.config.max_depth
+                        && required_keys(constraints).len() <= self.config.max_object_len
+                        && constraints
+                            .required
+                            .iter()
+                            .all(|key| key.chars().count() <= self.config.max_string_len)
+                }
+                _ => true,
+            }
+    }
+
+    /// The simplest value matching the shape `id` which may be placed inside
+    /// `depth` arrays or objects, or `None` if there isn't one. This is what
+    /// is generated when none of the random values match.
+    pub(crate) fn simplest(&self, id: ShapeId, depth: usize) -> Option<InternalJsonValue> {
+        self.simplest_within(id, depth, &|_| true, &mut { MAX_VISITED_NODES })
+    }
+
+    /// The simplest value matching the shape `id` which is also `accept`ed.
+    /// For scalars, a few values other than the simplest one are tried, e.g.
+    /// since the simplest value of a branch of a `oneOf` may also match
+    /// another branch.
+    fn simplest_within(
+        &self,
+        id: ShapeId,
+        depth: usize,
+        accept: &dyn Fn(&InternalJsonValue) -> bool,
+        visits: &mut usize,
+    ) -> Option<InternalJsonValue> {
+        if *visits == 0 || !self.shape.min_complexity(id).is_finite() {
+            return None;
+        }
+        *visits -= 1;
+        let by_complexity = |ids: &[ShapeId]| {
+            let mut ids = ids.to_vec();
+            ids.sort_by(|a, b| {
+                self.shape
+                    .min_complexity(*a)
+                    .total_cmp(&self.shape.min_complexity(*b))
+            });
+            ids
+        };
+        match self.shape.node(id) {
+            ShapeNode::Constraints(constraints) => {
+                let mut kinds = constraints
+                    .kinds
+                    .iter()
+                    .filter(|kind| self.may_generate_kind(constraints, *kind, depth))
+                    .collect::<Vec<_>>();
+                kinds.sort_by(|a, b| {
+                    self.shape
+                        .kind_min_complexity(constraints, *a)
+                        .total_cmp(&self.shape.kind_min_complexity(constraints, *b))
+                });
+                kinds.into_iter().find_map(|kind| {
+                    self.simplest_of_kind(constraints, kind, depth, visits)
+                        .into_iter()
+                        .find(|value| accept(value))
+                })
+            }
+            ShapeNode::Enum { values, .. } => values
+                .iter()
+                .filter(|value| self.is_within_limits(value, depth) && accept(value))
+                .min_by(|a, b| self.cplx(a).total_cmp(&self.cplx(b)))
+                .cloned(),
+            ShapeNode::AnyOf(ids) => by_complexity(ids)
+                .into_iter()
+                .find_map(|branch| self.simplest_within(branch, depth, accept, visits)),
+            ShapeNode::OneOf(ids) => {
+                let accept =
+                    |value: &InternalJsonValue| accept(value) && self.shape.matches(id, value);
+                by_complexity(ids)
+                    .into_iter()
+                    .find_map(|branch| self.simplest_within(branch, depth, &accept, visits))
+            }
+            ShapeNode::Usually { expected, .. } => self
+                .simplest_within(*expected, depth, accept, visits)
+                .or_else(|| self.simplest_within(ANY, depth, accept, visits)),
+        }
+    }
+
+    /// The simplest value of the given kind which satisfies `constraints` and
+    /// may be placed inside `depth` arrays or objects.
+    pub(crate) fn simplest_kind(
+        &self,
+        constraints: &Constraints,
+        kind: ValueKinds,
+        depth: usize,
+    ) -> Option<InternalJsonValue> {
+        if !self.may_generate_kind(constraints, kind, depth) {
+            return None;
+        }
+        self.simplest_of_kind(constraints, kind, depth, &mut { MAX_VISITED_NODES })
+            .into_iter()
+            .next()
+    }
+
+    /// The simplest values of the given kind which satisfy `constraints`,
+    /// simplest first.
+    fn simplest_of_kind(
+        &self,
+        constraints: &Constraints,
+        kind: ValueKinds,
+        depth: usize,
+        visits: &mut usize,
+    ) -> Vec<InternalJsonValue> {
+        match kind {
+            ValueKinds::NULL => vec![InternalJsonValue::Null],
+            ValueKinds::BOOL => [false, true]
+                .map(|inner| InternalJsonValue::Bool { inner })
+                .to_vec(),
+            ValueKinds::NUMBER => {
+                let (lowest, highest) = constraints.integer_bounds();
+                let mut numbers = constraints
+                    .simplest_number()
+                    .into_iter()
+                    .collect::<Vec<_>>();
+                numbers.extend([-1, 1, lowest, highest].map(integer_number));
+                if !constraints.integer {
+                    numbers.extend([-0.5, 0.5].map(|inner| InternalJsonNumber::Float { inner }));
+                }
+                numbers
+                    .into_iter()
+                    .filter(|number| constraints.matches_number(number))
+                    .map(|inner| InternalJsonValue::Number { inner })
+                    .collect()
+            }
+            ValueKinds::STRING => vec![InternalJsonValue::String {
+                inner: "a".repeat(constraints.min_length),
+            }],
+            ValueKinds::ARRAY => (0..constraints.min_items)
+                .map(|_| self.simplest_within(constraints.items, depth + 1, &|_| true, visits))
+                .collect::<Option<_>>()
+                .map(|inner| InternalJsonValue::Array { inner })
+                .into_iter()
+                .collect(),
+            _ => required_keys(constraints)
+                .into_iter()
+                .map(|key| {
+                    let id = self.shape.member_shape(constraints, key)?;
+                    let value = self.simplest_within(id, depth + 1, &|_| true, visits)?;
+                    Some((key.clone(), value))
+                })
+                .collect::<Option<_>>()
+                .map(|inner| InternalJsonValue::Object { inner })
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    /// Generates a value which matches the shape `id`, will be placed inside
+    /// `depth` arrays or objects and should have a complexity of roughly
+    /// `budget`.
+    pub(crate) fn generate(&self, id: ShapeId, depth: usize, budget: f64) -> InternalJsonValue {
+        if self.rng.f64() < self.config.value_dictionary_probability {
+            if let Some(value) = self.sample_value_dictionary(id, depth, budget) {
+                return value;
+            }
+        }
+        match self.shape.node(id) {
+            ShapeNode::Constraints(constraints) => {
+                self.generate_constrained(constraints, depth, budget)
+            }
+            ShapeNode::Enum { values, .. } => {
+                let candidates = values
+                    .iter()
+                    .filter(|value| {
+                        self.cplx(value) <= budget && self.is_within_limits(value, depth)
+                    })
+                    .collect::<Vec<_>>();
+                if candidates.is_empty() {
+                    values
+                        .iter()
+                        .min_by(|a, b| self.cplx(a).total_cmp(&self.cplx(b)))
+                        .expect("a shape which may be generated has at least one value")
+                        .clone()
+                } else {
+                    candidates[self.rng.usize(..candidates.len())].clone()
+                }
+            }
+            ShapeNode::AnyOf(ids) => {
+                self.generate(self.pick_branch(ids, depth, budget), depth, budget)
+            }
+            ShapeNode::Usually {
+                expected,
+                probability,
+            } => {
+                if self.rng.f64() < *probability && self.may_generate(*expected, depth) {
+                    self.generate(*expected, depth, budget)
+                } else {
+                    self.generate(ANY, depth, budget)
+                }
+            }
+            ShapeNode::OneOf(ids) => {
+                let mut value = self.generate(self.pick_branch(ids, depth, budget), depth, budget);
+                for _ in 1..MAX_GENERATION_ATTEMPTS {
+                    if self.shape.matches(id, &value) {
+                        return value;
+                    }
+                    value = self.generate(self.pick_branch(ids, depth, budget), depth, budget);
+                }
+                if self.shape.matches(id, &value) {
+                    value
+                } else {
+                    self.simplest(id, depth).unwrap_or(value)
+                }
+            }
+        }
+    }
+
+    /// Picks one of the branches of an `anyOf` or `oneOf` whose simplest value
+    /// fits in `budget`, or the simplest branch if there isn't one.
+    fn pick_branch(&self, ids: &[ShapeId], depth: usize, budget: f64) -> ShapeId {
+        let candidates = ids
+            .iter()
+            .filter(|id| self.may_generate(**id, depth))
+            .collect::<Vec<_>>();
+        let affordable = candidates
+            .iter()
+            .filter(|id| self.shape.min_complexity(***id) <= budget)
+            .collect::<Vec<_>>();
+        if affordable.is_empty() {
+            **candidates
+                .iter()
+                .min_by(|a, b| {
+                    self.shape
+                        .min_complexity(***a)
+                        .total_cmp(&self.shape.min_complexity(***b))
+                })
+                .expect("a shape which may be generated has at least one branch")
+        } else {
+            **affordable[self.rng.usize(..affordable.len())]
+        }
+    }
+
+    pub(crate) fn generate_constrained(
+        &self,
+        constraints: &Constraints,
+        depth: usize,
+        budget: f64,
+    ) -> InternalJsonValue {
+        let budget = finite_budget(budget);
+        let mut kinds = constraints
+            .kinds
+            .iter()
+            .filter(|kind| self.may_generate_kind(constraints, *kind, depth))
+            .fold(ValueKinds::NONE, |kinds, kind| kinds | kind);
+        if budget < 2.0 && kinds.intersects(ValueKinds::SCALARS) {
+            kinds = kinds.difference(ValueKinds::CONTAINERS);
+        }
+        let affordable = kinds
+            .iter()
+            .filter(|kind| self.shape.kind_min_complexity(constraints, *kind) <= budget)
+            .collect::<Vec<_>>();
+        let kind = if affordable.is_empty() {
+            kinds
+                .iter()
+                .min_by(|a, b| {
+                    self.shape
+                        .kind_min_complexity(constraints, *a)
+                        .total_cmp(&self.shape.kind_min_complexity(constraints, *b))
+                })
+                .expect("a shape which may be generated allows at least one kind of value")
+        } else {
+            affordable[self.rng.usize(..affordable.len())]
+        };
+        match kind {
+            ValueKinds::NULL => InternalJsonValue::Null,
+            ValueKinds::BOOL => InternalJsonValue::Bool {
+                inner: self.rng.bool(),
+            },
+            ValueKinds::NUMBER => InternalJsonValue::Number {
+                inner: self.generate_number(constraints),
+            },
+            ValueKinds::STRING => {
+                let mut string = self.generate_string(budget - 1.0);
+                self.fit_length(&mut string, constraints);
+                InternalJsonValue::String { inner: string }
+            }
+            ValueKinds::ARRAY => InternalJsonValue::Array {
+                inner: self.generate_array(constraints, depth, budget),
+            },
+            _ => InternalJsonValue::Object {
+                inner: self.generate_object(constraints, depth, budget),
+            },
+        }
+    }
+
+    fn generate_array(
+        &self,
+        constraints: &Constraints,
+        depth: usize,
+        budget: f64,
+    ) -> Vec<InternalJsonValue> {
+        let max_len = if self.may_generate(constraints.items, depth + 1) {
+            let affordable_len = (budget - 1.0) / self.shape.min_complexity(constraints.items);
+            constraints
+                .max_items
+                .min(self.config.max_array_len)
+                .min(affordable_len.max(0.0) as usize)
+        } else {
+            0
+        };
+        let len = self
+            .rng
+            .usize(constraints.min_items..=max_len.max(constraints.min_items));
+        let element_budget = (budget - 1.0) / len as f64;
+        (0..len)
+            .map(|_| self.generate(constraints.items, depth + 1, element_budget))
+            .collect()
+    }
+
+    /// Generates the required members, some of the other `properties` and
+    /// some additional members, in that order.
+    fn generate_object(
+        &self,
+        constraints: &Constraints,
+        depth: usize,
+        budget: f64,
+    ) -> Vec<(String, InternalJsonValue)> {
+        let mut keys: Vec<(&String, ShapeId)> = Vec::new();
+        for key in required_keys(constraints) {
+            let id = self
+                .shape
+                .member_shape(constraints, key)
+                .expect("a shape which may be generated allows its required members");
+            keys.push((key, id));
+        }
+        for (key, id) in &constraints.properties {
+            if !keys.iter().any(|(other, _)| *other == key)
+                && keys.len() < self.config.max_object_len
+                && self.may_generate(*id, depth + 1)
+                && self.rng.bool()
+            {
+                keys.push((key, *id));
+            }
+        }
+        let additional_len = match constraints.additional_properties {
+            Some(id) if self.may_generate(id, depth + 1) => self.rng.usize(
+                ..=self
+                    .config
+                    .max_object_len
+                    .saturating_sub(keys.len())
+                    .min(budget as usize / 2),
+            ),
+            _ => 0,
+        };
+        let member_budget = (budget - 1.0) / (keys.len() + additional_len) as f64;
+        let mut object = keys
+            .into_iter()
+            .map(|(key, id)| {
+                let value = self.generate(
+                    id,
+                    depth + 1,
+                    member_budget - self.complexity_model().of_key(key),
+                );
+                (key.clone(), value)
+            })
+            .collect::<Vec<_>>();
+        if let Some(id) = constraints.additional_properties {
+            for _ in 0..additional_len {
+                let (key, value) = self.generate_member(id, depth + 1, member_budget);
+                if !is_declared(constraints, &key) && !object.iter().any(|(other, _)| *other == key)
+                {
+                    object.push((key, value));
+                }
+            }
+        }
+        object
+    }
+
+    /// Generates a member with an arbitrary (or dictionary) key, whose value
+    /// matches the shape `id`.
+    fn generate_member(
+        &self,
+        id: ShapeId,
+        depth: usize,
+        budget: f64,
+    ) -> (String, InternalJsonValue) {
+        let key = self.generate_key(self.rng.f64() * (budget - 1.0));
+        let value = self.generate(id, depth, budget - self.complexity_model().of_key(&key));
+        (key, value)
+    }
+
+    /// Picks an entry of the value dictionary which matches the shape `id` (as
+    /// expected, see [`Shape::is_expected`]) and can be placed inside `depth`
+    /// arrays or objects without exceeding `budget`.
+    fn sample_value_dictionary(
+        &self,
+        id: ShapeId,
+        depth: usize,
+        budget: f64,
+    ) -> Option<InternalJsonValue> {
+        let dictionary = self.value_dictionary.as_ref()?;
+        (0..MAX_DICTIONARY_ATTEMPTS)
+            .map(|_| dictionary.sample())
+            .find(|(value, cplx)| {
+                *cplx <= budget
+                    && self.shape.is_expected(id, value)
+                    && self.is_within_limits(value, depth)
+            })
+            .map(|(value, _)| value.clone())
+    }
+
+    /// Picks a key from the dictionary or generates an arbitrary one.
+    pub(crate) fn generate_key(&self, budget: f64) -> String {
+        match &self.key_dictionary {
+            Some(dictionary) if self.rng.f64() >= self.config.arbitrary_key_probability => {
+                dictionary.sample().clone()
+            }
+            _ => self.generate_string(budget),
+        }
+    }
+
+    fn generate_number(&self, constraints: &Constraints) -> InternalJsonNumber {
+        if !constraints.integer
+            && constraints.minimum == Bound::Unbounded
+            && constraints.maximum == Bound::Unbounded
+        {
+            return self.number_mutator.random_arbitrary(f64::INFINITY).0;
+        }
+        (0..MAX_GENERATION_ATTEMPTS)
+            .map(|_| self.number_candidate(constraints))
+            .find(|number| constraints.matches_number(number))
+            .or_else(|| constraints.simplest_number())
+            .expect("a shape which may be generated allows at least one number")
+    }
+
+    /// Generates a number which is likely (but not certain) to satisfy the
+    /// bounds of `constraints`, favouring the bounds themselves.
+    fn number_candidate(&self, constraints: &Constraints) -> InternalJsonNumber {
+        let (lowest, highest) = constraints.integer_bounds();
+        match self.rng.u8(..4) {
+            0 => integer_number(if self.rng.bool() { lowest } else { highest }),
+            1 if lowest <= highest => integer_number(self.rng.i128(lowest..=highest)),
+            2 if !constraints.integer => {
+                let bound = |bound, unbounded| match bound {
+                    Bound::Included(bound) | Bound::Excluded(bound) => bound,
+                    Bound::Unbounded => unbounded,
+                };
+                let minimum = bound(constraints.minimum, f64::MIN);
+                let maximum = bound(constraints.maximum, f64::MAX);
+                let t = self.rng.f64();
+                InternalJsonNumber::Float {
+                    inner: minimum * (1.0 - t) + maximum * t,
+                }
+            }
+            _ => self.number_mutator.random_arbitrary(f64::INFINITY).0,
+        }
+    }
+
+    fn generate_string(&self, budget: f64) -> String {
+        self.string_mutator
+            .random_arbitrary(finite_budget(budget).max(0.0) * BITS_PER_BYTE)
+            .0
+    }
+
+    /// Truncates or extends `string` so that its length satisfies
+    /// `constraints` and the configuration, the latter taking precedence.
+    fn fit_length(&self, string: &mut String, constraints: &Constraints) {
+        let max_len = constraints.max_length.min(self.config.max_string_len);
+        let min_len = constraints.min_length.min(max_len);
+        if let Some((idx, _)) = string.char_indices().nth(max_len) {
+            string.truncate(idx);
+        }
+        let mut len = string.chars().count();
+        while len < min_len {
+            let missing = min_len - len;
+            let padding = self.generate_string(missing as f64);
+            if padding.is_empty() {
+                string.push(self.rng.char('a'..='z'));
+                len += 1;
+            } else {
+                string.extend(padding.chars().take(missing));
+                len += padding.chars().count().min(missing);
+            }
+        }
+    }
+
+    /// Applies a random mutation to a value which matches the shape `id` and
+    /// is inside `depth` arrays or objects, such that its complexity is
+    /// (ideally) no more than `budget`.
+    fn mutate_node(&self, node: &mut InternalJsonValue, id: ShapeId, depth: usize, budget: f64) {
+        let spare_budget = budget - self.cplx(node);
+        if kind_of(node).intersects(ValueKinds::SCALARS)
+            && self.rng.f64() < self.config.value_dictionary_probability
+        {
+            if let Some(value) = self.sample_value_dictionary(id, depth, budget) {
+                *node = value;
+                return;
+            }
+        }
+        let constraints = match self.shape.node(id) {
+            ShapeNode::Constraints(constraints) => constraints,
+            _ => {
+                *node = self.generate(id, depth, budget);
+                return;
+            }
+        };
+        match node {
+            InternalJsonValue::Bool { inner } if self.rng.bool() => *inner = !*inner,
+            InternalJsonValue::Number { inner } if self.rng.u8(..8) != 0 => {
+                self.mutate_number(inner);
+                if !constraints.matches_number(inner) {
+                    *inner = self.generate_number(constraints);
+                }
+            }
+            InternalJsonValue::String { inner } if self.rng.u8(..8) != 0 => {
+                self.mutate_string(inner, budget - 1.0);
+                self.fit_length(inner, constraints);
+            }
+            InternalJsonValue::Array { inner } if self.rng.u8(..8) != 0 => {
+                self.mutate_array(inner, constraints, depth, spare_budget)
+            }
+            InternalJsonValue::Object { inner } if self.rng.u8(..8) != 0 => {
+                self.mutate_object(inner, constraints, depth, spare_budget)
+            }
+            _ => *node = self.generate(id, depth, budget),
+        }
+    }
+
+    fn mutate_number(&self, number: &mut InternalJsonNumber) {
+        // only a number literal longer than the decimal mutator generates
+        // (which can be read from the corpus) is invalid
+        match self.number_mutator.validate_value(number) {
+            Some(mut cache) => {
+                self.number_mutator
+                    .random_mutate(number, &mut cache, f64::INFINITY);
+            }
+            None => *number = self.number_mutator.random_arbitrary(f64::INFINITY).0,
+        }
+    }
+
+    fn mutate_string(&self, string: &mut String, budget: f64) {
+        match self.string_mutator.validate_value(string) {
+            Some(mut cache) => {
+                self.string_mutator.random_mutate(
+                    string,
+                    &mut cache,
+                    finite_budget(budget).max(0.0) * BITS_PER_BYTE,
+                );
+            }
+            None => *string = self.generate_string(budget),
+        }
+    }
+
+    fn mutate_array(
+        &self,
+        array: &mut Vec<InternalJsonValue>,
+        constraints: &Constraints,
+        depth: usize,
+        spare_budget: f64,
+    ) {
+        match self.rng.u8(..3) {
+            0 if array.len() < constraints.max_items.min(self.config.max_array_len)
+                && self.may_generate(constraints.items, depth + 1) =>
+            {
+                let idx = self.rng.usize(..=array.len());
+                let element = self.generate(constraints.items, depth + 1, spare_budget);
+                array.insert(idx, element);
+            }
+            1 if array.len() > constraints.min_items => {
+                array.remove(self.rng.usize(..array.len()));
+            }
+            _ if array.len() >= 2 => {
+                let (a, b) = (self.rng.usize(..array.len()), self.rng.usize(..array.len()));
+                array.swap(a, b);
+            }
+            _ => array.truncate(constraints.min_items),
+        }
+    }
+
+    fn mutate_object(
+        &self,
+        object: &mut Vec<(String, InternalJsonValue)>,
+        constraints: &Constraints,
+        depth: usize,
+        spare_budget: f64,
+    ) {
+        let is_required = |key: &String| constraints.required.contains(key);
+        let removable = (0..object.len())
+            .filter(|idx| !is_required(&object[*idx].0))
+            .collect::<Vec<_>>();
+        let renamable = (0..object.len())
+            .filter(|idx| !is_declared(constraints, &object[*idx].0))
+            .collect::<Vec<_>>();
+        let max_len = self.config.max_object_len;
+        match self.rng.u8(..if self.duplicate_keys { 4 } else { 3 }) {
+            0 if object.len() < max_len => {
+                let absent = constraints
+                    .properties
+                    .iter()
+                    .filter(|(key, id)| {
+                        !object.iter().any(|(other, _)| other == key)
+                            && self.may_generate(*id, depth + 1)
+                    })
+                    .collect::<Vec<_>>();
+                let additional = constraints
+                    .additional_properties
+                    .filter(|id| self.may_generate(*id, depth + 1));
+                let idx = self.rng.usize(..=object.len());
+                if !absent.is_empty() && (additional.is_none() || self.rng.bool()) {
+                    let (key, id) = absent[self.rng.usize(..absent.len())];
+                    let budget = spare_budget - self.complexity_model().of_key(key);
+                    object.insert(idx, (key.clone(), self.generate(*id, depth + 1, budget)));
+                } else if let Some(id) = additional {
+                    let (key, value) = self.generate_member(id, depth + 1, spare_budget);
+                    if !is_declared(constraints, &key)
+                        && !object.iter().any(|(other, _)| *other == key)
+                    {
+                        object.insert(idx, (key, value));
+                    }
+                }
+            }
+            1 if !removable.is_empty() => {
+                object.remove(removable[self.rng.usize(..removable.len())]);
+            }
+            3 if !object.is_empty() && object.len() < max_len => {
+                let key = object[self.rng.usize(..object.len())].0.clone();
+                if let Some(id) = self.shape.member_shape(constraints, &key) {
+                    let budget = spare_budget - self.complexity_model().of_key(&key);
+                    let value = self.generate(id, depth + 1, budget);
+                    object.insert(self.rng.usize(..=object.len()), (key, value));
+                }
+            }
+            _ if !renamable.is_empty() && constraints.additional_properties.is_some() => {
+                let idx = renamable[self.rng.usize(..renamable.len())];
+                let key_budget = spare_budget + object[idx].0.len() as f64;
+                let mut key = object[idx].0.clone();
+                match &self.key_dictionary {
+                    Some(dictionary) if self.rng.f64() >= self.config.arbitrary_key_probability => {
+                        key = dictionary.sample().clone()
+                    }
+                    _ => self.mutate_string(&mut key, key_budget),
+                }
+                // the new key mustn't be that of another member
+                if !is_declared(constraints, &key) && !object.iter().any(|(other, _)| *other == key)
+                {
+                    object[idx].0 = key;
+                }
+            }
+            _ => object.retain(|(key, _)| is_required(key)),
+        }
+    }
+
+    /// Checks that a value which is inside `depth` arrays or objects respects
+    /// the limits in the configuration.
+    fn is_within_limits(&self, value: &InternalJsonValue, depth: usize) -> bool {
+        let is_valid_string =
+            |string: &String| string.chars().count() <= self.config.max_string_len;
+        match value {
+            InternalJsonValue::Null
+            | InternalJsonValue::Bool { .. }
+            | InternalJsonValue::Number { .. } => true,
+            InternalJsonValue::String { inner } => is_valid_string(inner),
+            InternalJsonValue::Array { inner } => {
+                depth < self.config.max_depth
+                    && inner.len() <= self.config.max_array_len
+                    && inner
+                        .iter()
+                        .all(|value| self.is_within_limits(value, depth + 1))
+            }
+            InternalJsonValue::Object { inner } => {
+                depth < self.config.max_depth
+                    && inner.len() <= self.config.max_object_len
+                    && inner.iter().all(|(key, value)| {
+                        is_valid_string(key) && self.is_within_limits(value, depth + 1)
+                    })
+            }
+        }
+    }
+
+    /// The shape of the elements or members of a value matching the shape
+    /// `id`, if they can be mutated on their own.
+    fn children_shape(&self, id: ShapeId) -> Option<&Constraints> {
+        match self.shape.node(id) {
+            ShapeNode::Constraints(constraints) => Some(constraints),
+            _ => None,
+        }
+    }
+
+    /// Counts the values in `value` (which matches the shape `id`) which can
+    /// be mutated on their own. The elements of an `enum` value cannot be.
+    fn count_nodes(&self, value: &InternalJsonValue, id: ShapeId) -> usize {
+        let id = self.shape.resolve(id, value);
+        let constraints = match self.children_shape(id) {
+            Some(constraints) => constraints,
+            None => return 1,
+        };
+        match value {
+            InternalJsonValue::Array { inner } => {
+                1 + inner
+                    .iter()
+                    .map(|value| self.count_nodes(value, constraints.items))
+                    .sum::<usize>()
+            }
+            InternalJsonValue::Object { inner } => {
+                1 + inner
+                    .iter()
+                    .filter_map(|(key, value)| {
+                        let id = self.shape.member_shape(constraints, key)?;
+                        Some(self.count_nodes(value, id))
+                    })
+                    .sum::<usize>()
+            }
+            _ => 1,
+        }
+    }
+
+    /// Finds the `idx`th value counted by [`count_nodes`](Self::count_nodes)
+    /// in a pre-order traversal of `value` (which matches the shape `id` and
+    /// is inside `depth` arrays or objects), along with its own shape and
+    /// depth. The indices of the elements and members leading to it are
+    /// pushed to `path`.
+    fn nth_node_mut<'a>(
+        &self,
+        value: &'a mut InternalJsonValue,
+        idx: &mut usize,
+        id: ShapeId,
+        depth: usize,
+        path: &mut Vec<usize>,
+    ) -> Option<(&'a mut InternalJsonValue, ShapeId, usize)> {
+        let id = self.shape.resolve(id, value);
+        if *idx == 0 {
+            return Some((value, id, depth));
+        }
+        *idx -= 1;
+        let constraints = self.children_shape(id)?;
+        let children = match value {
+            InternalJsonValue::Array { inner } => inner
+                .iter_mut()
+                .map(|value| (Some(constraints.items), value))
+                .collect::<Vec<_>>(),
+            InternalJsonValue::Object { inner } => inner
+                .iter_mut()
+                .map(|(key, value)| (self.shape.member_shape(constraints, key), value))
+                .collect(),
+            _ => return None,
+        };
+        for (child_idx, (child_id, child)) in children.into_iter().enumerate() {
+            // members which don't have a shape aren't counted
+            let Some(child_id) = child_id else {
+                continue;
+            };
+            path.push(child_idx);
+            if let Some(node) = self.nth_node_mut(child, idx, child_id, depth + 1, path) {
+                return Some(node);
+            }
+            path.pop();
+        }
+        None
+    }
+
+    /// Applies one of the mutations which reveal bugs in deserializers: copying
+    /// a value over another one, swapping two values, wrapping a value in an
+    /// array or object, unwrapping the only element or member of a container,
+    /// or changing the kind of a scalar while keeping its meaning (e.g. `5` to
+    /// `"5"`). Unlike [`mutate_node`](Self::mutate_node) this ignores the shape
+    /// of the values, so the result may be invalid.
+    ///
+    /// The values it replaces are added to `replaced`. Returns `false` if the
+    /// chosen mutation can't be applied to `value`, which is then unchanged.
+    pub(crate) fn mutate_structure(
+        &self,
+        value: &mut InternalJsonValue,
+        replaced: &mut Replaced,
+    ) -> bool {
+        let len = preorder(value).len();
+        match self.rng.u8(..5) {
+            0 => {
+                let source = node_mut(value, self.rng.usize(..len)).clone();
+                *replaced.node_mut(value, self.rng.usize(..len)) = source;
+            }
+            1 => {
+                let (a, b) = (self.rng.usize(..len), self.rng.usize(..len));
+                let (first, second) = (a.min(b), a.max(b));
+                // the second value must not be inside the first one (and the
+                // first one can't be inside the second one, which comes after
+                // it in a pre-order traversal)
+                if second < first + preorder(node_mut(value, first)).len() {
+                    return false;
+                }
+                let first_value = replaced.node_mut(value, first).clone();
+                let second_value = std::mem::replace(replaced.node_mut(value, second), first_value);
+                *node_mut(value, first) = second_value;
+            }
+            2 => {
+                let node = replaced.node_mut(value, self.rng.usize(..len));
+                let inner = std::mem::replace(node, InternalJsonValue::Null);
+                *node = if self.rng.bool() {
+                    InternalJsonValue::Array { inner: vec![inner] }
+                } else {
+                    let key = self.generate_key(self.rng.f64() * WRAPPER_KEY_BUDGET);
+                    InternalJsonValue::Object {
+                        inner: vec![(key, inner)],
+                    }
+                };
+            }
+            3 => {
+                let candidates = preorder(value)
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(_, node)| match node {
+                        InternalJsonValue::Array { inner } => inner.len() == 1,
+                        InternalJsonValue::Object { inner } => inner.len() == 1,
+                        _ => false,
+                    })
+                    .map(|(idx, _)| idx)
+                    .collect::<Vec<_>>();
+                if candidates.is_empty() {
+                    return false;
+                }
+                let node = replaced.node_mut(value, candidates[self.rng.usize(..candidates.len())]);
+                *node = match std::mem::replace(node, InternalJsonValue::Null) {
+                    InternalJsonValue::Array { mut inner } => inner.remove(0),
+                    InternalJsonValue::Object { mut inner } => inner.remove(0).1,
+                    _ => unreachable!("only containers with a single child are candidates"),
+                };
+            }
+            _ => {
+                let candidates = preorder(value)
+                    .into_iter()
+                    .enumerate()
+                    .filter_map(|(idx, node)| {
+                        let equivalents = equivalent_scalars(node);
+                        (!equivalents.is_empty()).then_some((idx, equivalents))
+                    })
+                    .collect::<Vec<_>>();
+                if candidates.is_empty() {
+                    return false;
+                }
+                let (idx, mut equivalents) = candidates[self.rng.usize(..candidates.len())].clone();
+                *replaced.node_mut(value, idx) =
+                    equivalents.swap_remove(self.rng.usize(..equivalents.len()));
+            }
+        }
+        true
+    }
+
+    /// Grafts `donor` (a value taken from another input) into `value`: as a
+    /// new element of one of its arrays, by copying one of the members of
+    /// `donor` into one of its objects, or by replacing one of its values.
+    ///
+    /// The values it replaces are added to `replaced`. Returns `false` if the
+    /// chosen graft can't be applied to `value`, which is then unchanged.
+    pub(crate) fn graft(
+        &self,
+        value: &mut InternalJsonValue,
+        donor: &InternalJsonValue,
+        replaced: &mut Replaced,
+    ) -> bool {
+        let nodes = preorder(value);
+        let arrays = nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| matches!(node, InternalJsonValue::Array { .. }))
+            .map(|(idx, _)| idx)
+            .collect::<Vec<_>>();
+        let objects = nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| matches!(node, InternalJsonValue::Object { .. }))
+            .map(|(idx, _)| idx)
+            .collect::<Vec<_>>();
+        let len = nodes.len();
+        match (self.rng.u8(..3), donor) {
+            (0, _) if !arrays.is_empty() => {
+                let node = replaced.node_mut(value, arrays[self.rng.usize(..arrays.len())]);
+                if let InternalJsonValue::Array { inner } = node {
+                    inner.insert(self.rng.usize(..=inner.len()), donor.clone());
+                }
+            }
+            (1, InternalJsonValue::Object { inner: members })
+                if !objects.is_empty() && !members.is_empty() =>
+            {
+                let (key, member) = &members[self.rng.usize(..members.len())];
+                let node = replaced.node_mut(value, objects[self.rng.usize(..objects.len())]);
+                if let InternalJsonValue::Object { inner } = node {
+                    match inner.iter_mut().find(|(other, _)| other == key) {
+                        Some((_, value)) => *value = member.clone(),
+                        None => inner.insert(
+                            self.rng.usize(..=inner.len()),
+                            (key.clone(), member.clone()),
+                        ),
+                    }
+                }
+            }
+            (2, _) => *replaced.node_mut(value, self.rng.usize(..len)) = donor.clone(),
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// The values replaced by a mutation, each with its path (the indices of the
+/// elements and members leading to it), so that the mutation can be undone
+/// and its complexity computed without copying the whole value.
+#[derive(Default)]
+pub(crate) struct Replaced(Vec<(Vec<usize>, InternalJsonValue)>);
+
+impl Replaced {
+    /// The same as [`node_mut`], but remembers the value so that it can be
+    /// restored. The values replaced by a mutation must not be inside one
+    /// another.
+    fn node_mut<'a>(
+        &mut self,
+        value: &'a mut InternalJsonValue,
+        idx: usize,
+    ) -> &'a mut InternalJsonValue {
+        let path = path_of(value, idx);
+        let node = at_path_mut(value, &path);
+        self.0.push((path, node.clone()));
+        node
+    }
+
+    fn push(&mut self, path: Vec<usize>, replaced: InternalJsonValue) {
+        self.0.push((path, replaced));
+    }
+
+    /// How much the complexity of `value` changed since the values were
+    /// replaced.
+    fn cplx_change(&self, value: &InternalJsonValue, model: ComplexityModel) -> f64 {
+        self.0
+            .iter()
+            .map(|(path, replaced)| {
+                let node = path.iter().fold(value, |value, idx| match value {
+                    InternalJsonValue::Array { inner } => &inner[*idx],
+                    InternalJsonValue::Object { inner } => &inner[*idx].1,
+                    _ => unreachable!("the path only goes through arrays and objects"),
+                });
+                model.of_internal(node) - model.of_internal(replaced)
+            })
+            .sum()
+    }
+
+    /// Puts the replaced values back into `value`, in the reverse order they
+    /// were replaced in.
+    fn restore(&mut self, value: &mut InternalJsonValue) {
+        while let Some((path, replaced)) = self.0.pop() {
+            *at_path_mut(value, &path) = replaced;
+        }
+    }
+}
+
+/// The path (see [`Replaced`]) to the `idx`th value of
+/// [`preorder(value)`](preorder).
+fn path_of(value: &InternalJsonValue, idx: usize) -> Vec<usize> {
+    fn find(value: &InternalJsonValue, idx: &mut usize, path: &mut Vec<usize>) -> bool {
+        if *idx == 0 {
+            return true;
+        }
+        *idx -= 1;
+        let children: Vec<&InternalJsonValue> = match value {
+            InternalJsonValue::Array { inner } => inner.iter().collect(),
+            InternalJsonValue::Object { inner } => inner.iter().map(|(_, value)| value).collect(),
+            _ => return false,
+        };
+        for (child_idx, child) in children.into_iter().enumerate() {
+            path.push(child_idx);
+            if find(child, idx, path) {
+                return true;
+            }
+            path.pop();
+        }
+        false
+    }
+    let mut path = Vec::new();
+    assert!(
+        find(value, &mut { idx }, &mut path),
+        "the index is smaller than the number of values"
+    );
+    path
+}
+
+/// Follows `path` (see [`Replaced`]) from `value`.
+fn at_path_mut<'a>(value: &'a mut InternalJsonValue, path: &[usize]) -> &'a mut InternalJsonValue {
+    path.iter().fold(value, |value, idx| match value {
+        InternalJsonValue::Array { inner } => &mut inner[*idx],
+        InternalJsonValue::Object { inner } => &mut inner[*idx].1,
+        _ => unreachable!("the path only goes through arrays and objects"),
+    })
+}
+
+/// Finds the `idx`th value of [`preorder(value)`](preorder).
+fn node_mut(value: &mut InternalJsonValue, idx: usize) -> &mut InternalJsonValue {
+    fn find<'a>(
+        value: &'a mut InternalJsonValue,
+        idx: &mut usize,
+    ) -> Option<&'a mut InternalJsonValue> {
+        if *idx == 0 {
+            return Some(value);
+        }
+        *idx -= 1;
+        match value {
+            InternalJsonValue::Array { inner } => {
+                inner.iter_mut().find_map(|value| find(value, idx))
+            }
+            InternalJsonValue::Object { inner } => {
+                inner.iter_mut().find_map(|(_, value)| find(value, idx))
+            }
+            _ => None,
+        }
+    }
+    find(value, &mut { idx }).expect("the index is smaller than the number of values")
+}
+
+/// Lists the values in `value` (including itself) in pre-order.
+fn preorder(value: &InternalJsonValue) -> Vec<&InternalJsonValue> {
+    let mut nodes = Vec::new();
+    let mut stack = vec![value];
+    while let Some(node) = stack.pop() {
+        nodes.push(node);
+        match node {
+            InternalJsonValue::Array { inner } => stack.extend(inner.iter().rev()),
+            InternalJsonValue::Object { inner } => {
+                stack.extend(inner.iter().rev().map(|(_, value)| value))
+            }
+            _ => {}
+        }
+    }
+    nodes
+}
+
+/// The scalars of another kind which a lenient deserializer might consider to
+/// be the same as `value`, e.g. `5` and `"5"`, or `true`, `1` and `"true"`.
+fn equivalent_scalars(value: &InternalJsonValue) -> Vec<InternalJsonValue> {
+    let string = |inner: String| InternalJsonValue::String { inner };
+    match value {
+        InternalJsonValue::Null => vec![string("null".to_owned())],
+        InternalJsonValue::Bool { inner } => vec![
+            string(inner.to_string()),
+            InternalJsonValue::Number {
+                inner: integer_number(*inner as i128),
+            },
+        ],
+        InternalJsonValue::Number { inner } => {
+            let mut equivalents = vec![string(
+                map_internal_number_to_serde(inner.clone()).to_string(),
+            )];
+            if let InternalJsonNumber::PosInt {
+                inner: inner @ (0 | 1),
+            } = inner
+            {
+                equivalents.push(InternalJsonValue::Bool { inner: *inner == 1 });
+            }
+            equivalents
+        }
+        InternalJsonValue::String { inner } => match inner.as_str() {
+            "null" => vec![InternalJsonValue::Null],
+            "true" | "false" => vec![InternalJsonValue::Bool {
+                inner: inner == "true",
+            }],
+            _ => match inner.parse::<serde_json::Number>() {
+                Ok(number) => vec![InternalJsonValue::Number {
+                    inner: map_serde_json_number_to_internal(&number),
+                }],
+                Err(_) => Vec::new(),
+            },
+        },
+        _ => Vec::new(),
+    }
+}
+
+/// The `required` keys of `constraints`, without duplicates.
+fn required_keys(constraints: &Constraints) -> Vec<&String> {
+    let mut keys = Vec::new();
+    for key in &constraints.required {
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+    keys
+}
+
+/// Returns `true` if `key` is one of the `properties` of `constraints`.
+fn is_declared(constraints: &Constraints, key: &str) -> bool {
+    constraints
+        .properties
+        .iter()
+        .any(|(property, _)| property == key)
+}
+
+/// Returns `true` if `value` contains an object with several members with the
+/// same key.
+fn has_duplicate_keys(value: &InternalJsonValue) -> bool {
+    match value {
+        InternalJsonValue::Array { inner } => inner.iter().any(has_duplicate_keys),
+        InternalJsonValue::Object { inner } => {
+            inner.iter().enumerate().any(|(idx, (key, value))| {
+                inner[..idx].iter().any(|(other, _)| other == key) || has_duplicate_keys(value)
+            })
+        }
+        _ => false,
+    }
+}
+
+fn visit_nodes<'a>(
+    value: &'a InternalJsonValue,
+    model: ComplexityModel,
+    visit: &mut dyn FnMut(&'a dyn Any, f64),
+) {
+    let children: Box<dyn Iterator<Item = &'a InternalJsonValue>> = match value {
+        InternalJsonValue::Array { inner } => Box::new(inner.iter()),
+        InternalJsonValue::Object { inner } => Box::new(inner.iter().map(|(_, value)| value)),
+        _ => return,
+    };
+    for child in children {
+        visit(child, model.of_internal(child));
+        visit_nodes(child, model, visit);
+    }
+}
+
+impl Mutator<InternalJsonValue> for InternalJsonValueMutator {
+    type Cache = f64;
+    type MutationStep = CrossoverStep<InternalJsonValue>;
+    type ArbitraryStep = ();
+    /// The values replaced by the mutation, and the complexity before it.
+    type UnmutateToken = (Replaced, f64);
+
+    fn default_arbitrary_step(&self) -> Self::ArbitraryStep {}
+
+    fn is_valid(&self, value: &InternalJsonValue) -> bool {
+        self.shape.matches(self.shape.root(), value)
+            && self.is_within_limits(value, 0)
+            && (self.duplicate_keys || !has_duplicate_keys(value))
+    }
+
+    fn validate_value(&self, value: &InternalJsonValue) -> Option<Self::Cache> {
+        self.is_valid(value).then(|| self.cplx(value))
+    }
+
+    fn default_mutation_step(
+        &self,
+        _value: &InternalJsonValue,
+        _cache: &Self::Cache,
+    ) -> Self::MutationStep {
+        CrossoverStep::default()
+    }
+
+    fn global_search_space_complexity(&self) -> f64 {
+        f64::INFINITY
+    }
+
+    fn max_complexity(&self) -> f64 {
+        f64::INFINITY
+    }
+
+    fn min_complexity(&self) -> f64 {
+        self.shape.min_complexity(self.shape.root())
+    }
+
+    fn complexity(&self, _value: &InternalJsonValue, cache: &Self::Cache) -> f64 {
+        *cache
+    }
+
+    fn ordered_arbitrary(
+        &self,
+        _step: &mut Self::ArbitraryStep,
+        max_cplx: f64,
+    ) -> Option<(InternalJsonValue, f64)> {
+        Some(self.random_arbitrary(max_cplx))
+    }
+
+    fn random_arbitrary(&self, max_cplx: f64) -> (InternalJsonValue, f64) {
+        let root = self.shape.root();
+        let value = (0..MAX_GENERATION_ATTEMPTS)
+            .map(|_| self.generate(root, 0, self.rng.f64() * max_cplx))
+            .find(|value| self.is_valid(value))
+            .or_else(|| self.simplest(root, 0))
+            .expect("the mutator is only built for shapes which have a simplest value");
+        let cplx = self.cplx(&value);
+        (value, cplx)
+    }
+
+    fn ordered_mutate(
+        &self,
+        value: &mut InternalJsonValue,
+        cache: &mut Self::Cache,
+        step: &mut Self::MutationStep,
+        subvalue_provider: &dyn SubValueProvider,
+        max_cplx: f64,
+    ) -> Option<(Self::UnmutateToken, f64)> {
+        if self.rng.f64() < CROSSOVER_PROBABILITY {
+            let budget = max_cplx - *cache;
+            // once every value of the provider has been tried, pick them at
+            // random
+            let donor = step
+                .get_next_subvalue(subvalue_provider, budget)
+                .or_else(|| {
+                    let (donor, cplx) = subvalue_provider
+                        .get_random_subvalue(TypeId::of::<InternalJsonValue>(), budget)?;
+                    Some((donor.downcast_ref()?, cplx))
+                });
+            if let Some((donor, _)) = donor {
+                let was_expected = self.is_expected(value);
+                let mut replaced = Replaced::default();
+                if self.graft(value, donor, &mut replaced) {
+                    let cplx = *cache + replaced.cplx_change(value, self.complexity_model());
+                    if cplx <= max_cplx && self.may_keep(was_expected, value) {
+                        let token = (replaced, *cache);
+                        *cache = cplx;
+                        return Some((token, cplx));
+                    }
+                    replaced.restore(value);
+                }
+            }
+        }
+        Some(self.random_mutate(value, cache, max_cplx))
+    }
+
+    fn random_mutate(
+        &self,
+        value: &mut InternalJsonValue,
+        cache: &mut Self::Cache,
+        max_cplx: f64,
+    ) -> (Self::UnmutateToken, f64) {
+        let was_expected = self.is_expected(value);
+        let mut replaced = Replaced::default();
+        for _ in 0..MAX_MUTATION_ATTEMPTS {
+            if self.rng.f64() < STRUCTURAL_MUTATION_PROBABILITY
+                && self.mutate_structure(value, &mut replaced)
+            {
+                let cplx = *cache + replaced.cplx_change(value, self.complexity_model());
+                if cplx <= max_cplx && self.may_keep(was_expected, value) {
+                    let token = (replaced, *cache);
+                    *cache = cplx;
+                    return (token, cplx);
+                }
+                replaced.restore(value);
+                continue;
+            }
+            let root = self.shape.root();
+            let mut idx = self.rng.usize(..self.count_nodes(value, root));
+            let mut path = Vec::new();
+            let (node, id, depth) = self
+                .nth_node_mut(value, &mut idx, root, 0, &mut path)
+                .expect("the index is smaller than the number of nodes");
+            let node_cplx = self.cplx(node);
+            replaced.push(path, node.clone());
+            let budget = max_cplx - (*cache - node_cplx);
+            self.mutate_node(node, id, depth, budget);
+
+            let cplx = *cache - node_cplx + self.cplx(node);
+            if cplx <= max_cplx && self.may_keep(was_expected, value) {
+                let token = (replaced, *cache);
+                *cache = cplx;
+                return (token, cplx);
+            }
+            replaced.restore(value);
+        }
+        ((replaced, *cache), *cache)
+    }
+
+    fn unmutate(
+        &self,
+        value: &mut InternalJsonValue,
+        cache: &mut Self::Cache,
+        t: Self::UnmutateToken,
+    ) {
+        let (mut replaced, cplx) = t;
+        replaced.restore(value);
+        *cache = cplx;
+    }
+
+    fn visit_subvalues<'a>(
+        &self,
+        value: &'a InternalJsonValue,
+        _cache: &'a Self::Cache,
+        visit: &mut dyn FnMut(&'a dyn Any, f64),
+    ) {
+        visit_nodes(value, self.complexity_model(), visit);
+    }
+}
@@ -0,0 +1,46 @@
+//! Writes the documents generated by
+//! [`ndjson_value_mutator`](crate::ndjson_value_mutator) into a stream, each
+//! followed by one of a few line breaks, and reads them (along with the line
+//! breaks) back from a stream.
+
+use serde_json::Value;
+
+/// What may follow a document: a line feed or a carriage return and line feed,
+/// possibly followed by a blank line (which may contain whitespace).
+pub(crate) const SEPARATORS: [&str; 6] = ["\n", "\r\n", "\n\n", "\r\n\r\n", "\n \n", "\n\t\r\n"];
+
+/// Writes each document followed by its separator (an index into
+/// [`SEPARATORS`]), except for the last one if the stream isn't `terminated`.
+pub(crate) fn write(records: &[(Value, u8)], terminated: bool) -> String {
+    let mut stream = String::new();
+    for (i, (value, separator)) in records.iter().enumerate() {
+        stream.push_str(&value.to_string());
+        if terminated || i + 1 < records.len() {
+            stream.push_str(SEPARATORS[*separator as usize]);
+        }
+    }
+    stream
+}
+
+/// The documents of `stream` along with the separator after each of them, and
+/// whether the last one is terminated, if `stream` could have been written by
+/// [`write`] from `values`.
+pub(crate) fn read(stream: &str, values: &[Value]) -> Option<(Vec<(Value, u8)>, bool)> {
+    let mut rest = stream;
+    let mut records = Vec::with_capacity(values.len());
+    for value in values {
+        rest = rest.strip_prefix(value.to_string().as_str())?;
+        if rest.is_empty() && records.len() + 1 == values.len() {
+            records.push((value.clone(), 0));
+            return Some((records, false));
+        }
+        // documents never start with whitespace, so the longest separator is
+        // the one which was written
+        let separator = (0..SEPARATORS.len())
+            .filter(|i| rest.starts_with(SEPARATORS[*i]))
+            .max_by_key(|i| SEPARATORS[*i].len())?;
+        rest = &rest[SEPARATORS[separator].len()..];
+        records.push((value.clone(), separator as u8));
+    }
+    rest.is_empty().then_some((records, true))
+}
@@ -0,0 +1,825 @@
+//! Generates values which match a JSON Schema except for exactly one
+//! constraint. A value matching the schema is generated (or mutated) by an
+//! [`InternalJsonValueMutator`], then a constraint which applies to it is
+//! picked and the value is changed so that it violates that constraint only.
+//!
+//! Constraints inside a `oneOf` or `anyOf` with several branches are never
+//! violated, since the value might then match another branch (or fail every
+//! branch for several reasons).
+
+use std::any::Any;
+use std::fmt;
+use std::ops::Bound;
+
+use fuzzcheck::fastrand::Rng;
+use fuzzcheck::{Mutator, SubValueProvider};
+use serde::{Deserialize, Serialize};
+
+use crate::mutator::{InternalJsonValueMutator, MAX_VISITED_NODES};
+use crate::shape::{integer_number, Constraints, Shape, ShapeId, ShapeNode, ANY};
+use crate::{InternalJsonNumber, InternalJsonValue, SchemaError, ValueKinds};
+
+/// How many times a value is generated or mutated before giving up on finding
+/// a constraint which it can be made to violate.
+const MAX_VIOLATION_ATTEMPTS: usize = 256;
+
+/// How many keys are generated before giving up on finding one which an object
+/// doesn't have and its schema doesn't declare.
+const MAX_KEY_ATTEMPTS: usize = 16;
+
+/// The constraint of a JSON Schema which a value generated by
+/// [`json_schema_near_miss_mutator`](crate::json_schema_near_miss_mutator)
+/// violates.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SchemaViolation {
+    /// A JSON pointer to the part of the value which violates the constraint
+    /// (e.g. `/items/0`), which can be passed to
+    /// [`Value::pointer`](serde_json::Value::pointer).
+    pub pointer: String,
+    pub kind: ViolationKind,
+}
+
+impl fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "the value at `{}` violates `{}`",
+            self.pointer,
+            self.kind.keyword()
+        )?;
+        match &self.kind {
+            ViolationKind::Required { key } | ViolationKind::AdditionalProperty { key } => {
+                write!(f, " (`{key}`)")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ViolationKind {
+    /// The value is not of one of the `type`s of the schema. This includes
+    /// numbers with a fractional part where an `integer` is expected.
+    Type,
+    /// The value is not in the `enum` of the schema (or is not its `const`).
+    Enum,
+    /// The number is smaller than `minimum` (or not greater than
+    /// `exclusiveMinimum`).
+    Minimum,
+    /// The number is greater than `maximum` (or not smaller than
+    /// `exclusiveMaximum`).
+    Maximum,
+    MinLength,
+    MaxLength,
+    MinItems,
+    MaxItems,
+    /// The object doesn't have the required member `key`.
+    Required {
+        key: String,
+    },
+    /// The object has the member `key`, although `additionalProperties` is
+    /// `false` and `key` is not one of its `properties`.
+    AdditionalProperty {
+        key: String,
+    },
+}
+
+impl ViolationKind {
+    /// The schema keyword whose constraint is violated.
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            Self::Type => "type",
+            Self::Enum => "enum",
+            Self::Minimum => "minimum",
+            Self::Maximum => "maximum",
+            Self::MinLength => "minLength",
+            Self::MaxLength => "maxLength",
+            Self::MinItems => "minItems",
+            Self::MaxItems => "maxItems",
+            Self::Required { .. } => "required",
+            Self::AdditionalProperty { .. } => "additionalProperties",
+        }
+    }
+}
+
+/// A value which matches the schema except for the constraint described by
+/// `violation`.
+#[derive(Clone)]
+pub(crate) struct NearMiss {
+    broken: InternalJsonValue,
+    violation: SchemaViolation,
+}
+
+impl NearMiss {
+    pub(crate) fn new(broken: InternalJsonValue, violation: SchemaViolation) -> Self {
+        Self { broken, violation }
+    }
+
+    pub(crate) fn broken(&self) -> &InternalJsonValue {
+        &self.broken
+    }
+
+    pub(crate) fn violation(&self) -> &SchemaViolation {
+        &self.violation
+    }
+}
+
+/// The cache of a [`NearMiss`] holds the valid value it was made from, to which
+/// mutations are applied before breaking one of its constraints again. For a
+/// near miss which wasn't generated by the mutator (e.g. one loaded from a
+/// corpus), it is recreated by repairing the broken value.
+#[derive(Clone)]
+pub(crate) struct NearMissCache {
+    valid: InternalJsonValue,
+    valid_cplx: f64,
+    cplx: f64,
+}
+
+/// A constraint which a value (somewhere inside the value being broken) may
+/// be changed to violate.
+struct Candidate {
+    /// The indices of the elements and members leading to the value.
+    path: Vec<usize>,
+    pointer: String,
+    id: ShapeId,
+    depth: usize,
+    kind: ViolationKind,
+}
+
+/// Returns `true` if the shape has a constraint which a value can violate on
+/// its own, i.e. outside of a `oneOf` or `anyOf` with several branches.
+pub(crate) fn has_violable_constraint(shape: &Shape) -> bool {
+    let mut visited = vec![shape.root()];
+    let mut stack = vec![shape.root()];
+    while let Some(id) = stack.pop() {
+        let children = match shape.node(id) {
+            ShapeNode::Enum { .. } => return true,
+            ShapeNode::AnyOf(ids) | ShapeNode::OneOf(ids) if ids.len() == 1 => ids.clone(),
+            ShapeNode::AnyOf(_) | ShapeNode::OneOf(_) | ShapeNode::Usually { .. } => Vec::new(),
+            ShapeNode::Constraints(constraints) => {
+                if constraints.kinds != ValueKinds::ALL
+                    || constraints.integer
+                    || constraints.minimum != Bound::Unbounded
+                    || constraints.maximum != Bound::Unbounded
+                    || constraints.min_length > 0
+                    || constraints.max_length != usize::MAX
+                    || constraints.min_items > 0
+                    || constraints.max_items != usize::MAX
+                    || !constraints.required.is_empty()
+                    || constraints.additional_properties.is_none()
+                {
+                    return true;
+                }
+                let mut children = vec![constraints.items];
+                children.extend(constraints.properties.iter().map(|(_, id)| *id));
+                children.extend(constraints.additional_properties);
+                children
+            }
+        };
+        for child in children {
+            if !visited.contains(&child) {
+                visited.push(child);
+                stack.push(child);
+            }
+        }
+    }
+    false
+}
+
+pub(crate) struct InternalNearMissMutator {
+    inner: InternalJsonValueMutator,
+    /// The near miss (and its complexity) generated when none of the values
+    /// generated at random have a constraint which can be violated, which is
+    /// found when the mutator is built.
+    fallback: Option<(NearMiss, f64)>,
+    rng: Rng,
+}
+
+impl InternalNearMissMutator {
+    /// Returns [`SchemaError::NothingToViolate`] if no value matching the
+    /// shape of `inner` can be made to violate one of its constraints.
+    pub(crate) fn new(inner: InternalJsonValueMutator) -> Result<Self, SchemaError> {
+        let mut mutator = Self {
+            inner,
+            fallback: None,
+            rng: Rng::new(),
+        };
+        mutator.fallback = Some(
+            mutator
+                .find_fallback()
+                .ok_or(SchemaError::NothingToViolate)?,
+        );
+        Ok(mutator)
+    }
+
+    /// Breaks a constraint of a value which is chosen so that it has one,
+    /// rather than generated at random.
+    fn find_fallback(&self) -> Option<(NearMiss, f64)> {
+        let root = self.shape().root();
+        let valid = self
+            .violable(root, 0, &mut { MAX_VISITED_NODES })
+            .filter(|valid| self.inner.is_valid(valid))?;
+        let budget = self.inner.cplx(&valid);
+        let near_miss = (0..MAX_VIOLATION_ATTEMPTS).find_map(|_| self.violate(&valid, budget))?;
+        let cplx = self.inner.cplx(&near_miss.broken);
+        Some((near_miss, cplx))
+    }
+
+    /// A simple value matching the shape `id` (and placed inside `depth`
+    /// arrays or objects) which has a constraint that can be violated, either
+    /// its own or that of one of its elements or members.
+    fn violable(&self, id: ShapeId, depth: usize, visits: &mut usize) -> Option<InternalJsonValue> {
+        if *visits == 0 {
+            return None;
+        }
+        *visits -= 1;
+        let id = self.skip_references(id);
+        if id == ANY {
+            return None;
+        }
+        let constraints = match self.shape().node(id) {
+            ShapeNode::Enum { .. } => return self.inner.simplest(id, depth),
+            ShapeNode::Constraints(constraints) => constraints,
+            _ => return None,
+        };
+        if constraints.kinds != ValueKinds::ALL || constraints.integer {
+            return self.inner.simplest(id, depth);
+        }
+        let own = [
+            (
+                ValueKinds::NUMBER,
+                constraints.minimum != Bound::Unbounded || constraints.maximum != Bound::Unbounded,
+            ),
+            (
+                ValueKinds::STRING,
+                constraints.min_length > 0 || constraints.max_length != usize::MAX,
+            ),
+            (
+                ValueKinds::ARRAY,
+                constraints.min_items > 0 || constraints.max_items != usize::MAX,
+            ),
+            (
+                ValueKinds::OBJECT,
+                !constraints.required.is_empty() || constraints.additional_properties.is_none(),
+            ),
+        ];
+        if let Some(value) = own
+            .into_iter()
+            .filter(|(_, has_constraint)| *has_constraint)
+            .find_map(|(kind, _)| self.inner.simplest_kind(constraints, kind, depth))
+        {
+            return Some(value);
+        }
+        // every array and object may be empty here, and may contain a value
+        // with a constraint
+        if let Some(element) = self.violable(constraints.items, depth + 1, visits) {
+            return Some(InternalJsonValue::Array {
+                inner: vec![element],
+            });
+        }
+        let mut members = constraints.properties.clone();
+        if let Some(additional) = constraints.additional_properties {
+            let key = (0..)
+                .map(|idx: usize| idx.to_string())
+                .find(|key| !constraints.properties.iter().any(|(other, _)| other == key))
+                .expect("there are fewer properties than integers");
+            members.push((key, additional));
+        }
+        members.into_iter().find_map(|(key, id)| {
+            let member = self.violable(id, depth + 1, visits)?;
+            Some(InternalJsonValue::Object {
+                inner: vec![(key, member)],
+            })
+        })
+    }
+
+    fn shape(&self) -> &Shape {
+        self.inner.shape()
+    }
+
+    /// Follows the `anyOf` and `oneOf` nodes with a single branch, which are
+    /// created for each `$ref`.
+    fn skip_references(&self, mut id: ShapeId) -> ShapeId {
+        while let ShapeNode::AnyOf(ids) | ShapeNode::OneOf(ids) = self.shape().node(id) {
+            if ids.len() != 1 {
+                break;
+            }
+            id = ids[0];
+        }
+        id
+    }
+
+    /// Breaks a randomly chosen constraint which applies to `valid`, or returns
+    /// `None` if there isn't one.
+    fn violate(&self, valid: &InternalJsonValue, budget: f64) -> Option<NearMiss> {
+        let mut candidates = Vec::new();
+        self.collect_candidates(
+            valid,
+            self.shape().root(),
+            &mut Vec::new(),
+            String::new(),
+            0,
+            &mut candidates,
+        );
+        while !candidates.is_empty() {
+            let candidate = candidates.swap_remove(self.rng.usize(..candidates.len()));
+            let mut broken = valid.clone();
+            let node = candidate
+                .path
+                .iter()
+                .fold(&mut broken, |value, idx| match value {
+                    InternalJsonValue::Array { inner } => &mut inner[*idx],
+                    InternalJsonValue::Object { inner } => &mut inner[*idx].1,
+                    _ => unreachable!("the path only goes through arrays and objects"),
+                });
+            let Some(kind) = self.break_node(node, &candidate, budget) else {
+                continue;
+            };
+            // breaking the constraint mustn't have broken another one
+            if !self.matches_relaxed(node, candidate.id, &kind) {
+                continue;
+            }
+            if !self.shape().matches(self.shape().root(), &broken) {
+                return Some(NearMiss {
+                    broken,
+                    violation: SchemaViolation {
+                        pointer: candidate.pointer,
+                        kind,
+                    },
+                });
+            }
+        }
+        None
+    }
+
+    /// Returns `true` if `node` matches the shape `id` once the constraint
+    /// violated by `kind` is removed from it.
+    fn matches_relaxed(&self, node: &InternalJsonValue, id: ShapeId, kind: &ViolationKind) -> bool {
+        let shape = self.shape();
+        let mut constraints = match shape.node(id) {
+            ShapeNode::Enum { constraints, .. } => return shape.matches(*constraints, node),
+            ShapeNode::Constraints(constraints) => constraints.clone(),
+            _ => unreachable!("candidates are only found in enums and constraints"),
+        };
+        match kind {
+            ViolationKind::Type => {
+                constraints.kinds = ValueKinds::ALL;
+                constraints.integer = false;
+            }
+            ViolationKind::Enum => unreachable!("enum candidates are only found in enums"),
+            ViolationKind::Minimum => constraints.minimum = Bound::Unbounded,
+            ViolationKind::Maximum => constraints.maximum = Bound::Unbounded,
+            ViolationKind::MinLength => constraints.min_length = 0,
+            ViolationKind::MaxLength => constraints.max_length = usize::MAX,
+            ViolationKind::MinItems => constraints.min_items = 0,
+            ViolationKind::MaxItems => constraints.max_items = usize::MAX,
+            ViolationKind::Required { key } => constraints.required.retain(|other| other != key),
+            ViolationKind::AdditionalProperty { key } => {
+                constraints.properties.push((key.clone(), ANY))
+            }
+        }
+        shape.matches_constraints(&constraints, node)
+    }
+
+    /// Undoes the violation of `near_miss` by generating a new value in place
+    /// of the one which violates the constraint (or by adding the missing
+    /// member, or removing the additional one). Returns `None` if the
+    /// violation doesn't describe the broken value.
+    fn repair(&self, near_miss: &NearMiss) -> Option<InternalJsonValue> {
+        let shape = self.shape();
+        let mut valid = near_miss.broken.clone();
+        let mut node = &mut valid;
+        let mut id = self.skip_references(shape.root());
+        let mut depth = 0;
+        let pointer = &near_miss.violation.pointer;
+        let segments = match pointer.strip_prefix('/') {
+            Some(segments) => segments.split('/').collect(),
+            None if pointer.is_empty() => Vec::new(),
+            None => return None,
+        };
+        for segment in segments {
+            let segment = segment.replace("~1", "/").replace("~0", "~");
+            let ShapeNode::Constraints(constraints) = shape.node(id) else {
+                return None;
+            };
+            (node, id) = match node {
+                InternalJsonValue::Array { inner } => (
+                    inner.get_mut(segment.parse::<usize>().ok()?)?,
+                    constraints.items,
+                ),
+                InternalJsonValue::Object { inner } => {
+                    let (_, member) = inner.iter_mut().find(|(key, _)| *key == segment)?;
+                    (member, shape.member_shape(constraints, &segment)?)
+                }
+                _ => return None,
+            };
+            id = self.skip_references(id);
+            depth += 1;
+        }
+        let budget = self.inner.cplx(node);
+        match (&near_miss.violation.kind, node) {
+            (ViolationKind::Required { key }, InternalJsonValue::Object { inner }) => {
+                let ShapeNode::Constraints(constraints) = shape.node(id) else {
+                    return None;
+                };
+                let member = shape.member_shape(constraints, key)?;
+                inner.push((key.clone(), self.inner.generate(member, depth + 1, budget)));
+            }
+            (ViolationKind::AdditionalProperty { key }, InternalJsonValue::Object { inner }) => {
+                inner.retain(|(other, _)| other != key);
+            }
+            (ViolationKind::Required { .. } | ViolationKind::AdditionalProperty { .. }, _) => {
+                return None
+            }
+            (_, node) => *node = self.inner.generate(id, depth, budget),
+        }
+        self.inner.is_valid(&valid).then_some(valid)
+    }
+
+    fn collect_candidates(
+        &self,
+        value: &InternalJsonValue,
+        id: ShapeId,
+        path: &mut Vec<usize>,
+        pointer: String,
+        depth: usize,
+        candidates: &mut Vec<Candidate>,
+    ) {
+        let shape = self.shape();
+        let id = self.skip_references(id);
+        let constraints = match shape.node(id) {
+            ShapeNode::Constraints(constraints) => constraints,
+            ShapeNode::Enum { .. } => {
+                candidates.push(Candidate {
+                    path: path.clone(),
+                    pointer,
+                    id,
+                    depth,
+                    kind: ViolationKind::Enum,
+                });
+                return;
+            }
+            ShapeNode::AnyOf(_) | ShapeNode::OneOf(_) | ShapeNode::Usually { .. } => return,
+        };
+        let mut push = |kind| {
+            candidates.push(Candidate {
+                path: path.clone(),
+                pointer: pointer.clone(),
+                id,
+                depth,
+                kind,
+            })
+        };
+        if constraints.kinds != ValueKinds::ALL || constraints.integer {
+            push(ViolationKind::Type);
+        }
+        match value {
+            InternalJsonValue::Number { .. } => {
+                if constraints.minimum != Bound::Unbounded {
+                    push(ViolationKind::Minimum);
+                }
+                if constraints.maximum != Bound::Unbounded {
+                    push(ViolationKind::Maximum);
+                }
+            }
+            InternalJsonValue::String { .. } => {
+                if constraints.min_length > 0 {
+                    push(ViolationKind::MinLength);
+                }
+                if constraints.max_length != usize::MAX {
+                    push(ViolationKind::MaxLength);
+                }
+            }
+            InternalJsonValue::Array { inner } => {
+                if constraints.min_items > 0 {
+                    push(ViolationKind::MinItems);
+                }
+                if constraints.max_items != usize::MAX
+                    && self.inner.may_generate(constraints.items, depth + 1)
+                {
+                    push(ViolationKind::MaxItems);
+                }
+                for (idx, element) in inner.iter().enumerate() {
+                    path.push(idx);
+                    self.collect_candidates(
+                        element,
+                        constraints.items,
+                        path,
+                        format!("{pointer}/{idx}"),
+                        depth + 1,
+                        candidates,
+                    );
+                    path.pop();
+                }
+            }
+            InternalJsonValue::Object { inner } => {
+                for key in &constraints.required {
+                    push(ViolationKind::Required { key: key.clone() });
+                }
+                if constraints.additional_properties.is_none() {
+                    push(ViolationKind::AdditionalProperty { key: String::new() });
+                }
+                for (idx, (key, member)) in inner.iter().enumerate() {
+                    let Some(member_id) = shape.member_shape(constraints, key) else {
+                        continue;
+                    };
+                    path.push(idx);
+                    self.collect_candidates(
+                        member,
+                        member_id,
+                        path,
+                        format!("{pointer}/{}", key.replace('~', "~0").replace('/', "~1")),
+                        depth + 1,
+                        candidates,
+                    );
+                    path.pop();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Changes `node` so that it violates the constraint of `candidate`, and
+    /// returns the violation (which is only different from the candidate's for
+    /// additional properties, whose key is decided here).
+    fn break_node(
+        &self,
+        node: &mut InternalJsonValue,
+        candidate: &Candidate,
+        budget: f64,
+    ) -> Option<ViolationKind> {
+        let shape = self.shape();
+        let depth = candidate.depth;
+        let constraints = match shape.node(candidate.id) {
+            ShapeNode::Enum { constraints, .. } => {
+                // a value of the right type which isn't one of the enum's
+                *node = (0..MAX_VIOLATION_ATTEMPTS)
+                    .map(|_| self.inner.generate(*constraints, depth, budget))
+                    .find(|value| !shape.matches(candidate.id, value))?;
+                return Some(ViolationKind::Enum);
+            }
+            ShapeNode::Constraints(constraints) => constraints,
+            _ => unreachable!("candidates are only found in enums and constraints"),
+        };
+        match (&candidate.kind, node) {
+            (ViolationKind::Type, node) => {
+                let kinds = ValueKinds::ALL
+                    .difference(constraints.kinds)
+                    .iter()
+                    .collect::<Vec<_>>();
+                let fractional = constraints
+                    .integer
+                    .then(|| self.fractional_number(constraints))
+                    .flatten();
+                if fractional.is_some() && (kinds.is_empty() || self.rng.bool()) {
+                    *node = InternalJsonValue::Number { inner: fractional? };
+                } else if !kinds.is_empty() {
+                    let kind = kinds[self.rng.usize(..kinds.len())];
+                    *node = self.inner.generate_constrained(
+                        &Constraints::of_kinds(kind, ANY, ANY),
+                        depth,
+                        budget,
+                    );
+                } else {
+                    return None;
+                }
+            }
+            (ViolationKind::Minimum, InternalJsonValue::Number { inner }) => {
+                *inner = self.out_of_range_number(constraints, true)?;
+            }
+            (ViolationKind::Maximum, InternalJsonValue::Number { inner }) => {
+                *inner = self.out_of_range_number(constraints, false)?;
+            }
+            (ViolationKind::MinLength, InternalJsonValue::String { inner }) => {
+                if let Some((idx, _)) = inner.char_indices().nth(constraints.min_length - 1) {
+                    inner.truncate(idx);
+                }
+            }
+            (ViolationKind::MaxLength, InternalJsonValue::String { inner }) => {
+                let missing = (constraints.max_length + 1).saturating_sub(inner.chars().count());
+                inner.extend((0..missing).map(|_| self.rng.char('a'..='z')));
+            }
+            (ViolationKind::MinItems, InternalJsonValue::Array { inner }) => {
+                inner.truncate(constraints.min_items - 1);
+            }
+            (ViolationKind::MaxItems, InternalJsonValue::Array { inner }) => {
+                let missing = (constraints.max_items + 1).saturating_sub(inner.len());
+                let element_budget = budget / missing as f64;
+                inner.extend((0..missing).map(|_| {
+                    self.inner
+                        .generate(constraints.items, depth + 1, element_budget)
+                }));
+            }
+            (ViolationKind::Required { key }, InternalJsonValue::Object { inner }) => {
+                inner.retain(|(other, _)| other != key);
+            }
+            (ViolationKind::AdditionalProperty { .. }, InternalJsonValue::Object { inner }) => {
+                let key = (0..MAX_KEY_ATTEMPTS)
+                    .map(|_| self.inner.generate_key(self.rng.f64() * budget))
+                    .find(|key| {
+                        !constraints.properties.iter().any(|(other, _)| other == key)
+                            && !inner.iter().any(|(other, _)| other == key)
+                    })?;
+                let value = self.inner.generate(ANY, depth + 1, budget);
+                let idx = self.rng.usize(..=inner.len());
+                inner.insert(idx, (key.clone(), value));
+                return Some(ViolationKind::AdditionalProperty { key });
+            }
+            _ => unreachable!("candidates are only found in values of the right kind"),
+        }
+        Some(candidate.kind.clone())
+    }
+
+    /// A number with a fractional part which satisfies every constraint other
+    /// than being an integer.
+    fn fractional_number(&self, constraints: &Constraints) -> Option<InternalJsonNumber> {
+        let (lowest, highest) = constraints.integer_bounds();
+        let integer = if self.rng.bool() { lowest } else { highest };
+        let number = InternalJsonNumber::Float {
+            inner: integer as f64 + if integer == lowest { 0.5 } else { -0.5 },
+        };
+        let any_number = Constraints {
+            integer: false,
+            ..constraints.clone()
+        };
+        (any_number.matches_number(&number) && !constraints.matches_number(&number))
+            .then_some(number)
+    }
+
+    /// A number which is just below the minimum (or above the maximum), but
+    /// otherwise satisfies the constraints.
+    fn out_of_range_number(
+        &self,
+        constraints: &Constraints,
+        below: bool,
+    ) -> Option<InternalJsonNumber> {
+        let (bound, unbounded) = if below {
+            (
+                constraints.minimum,
+                Constraints {
+                    minimum: Bound::Unbounded,
+                    ..constraints.clone()
+                },
+            )
+        } else {
+            (
+                constraints.maximum,
+                Constraints {
+                    maximum: Bound::Unbounded,
+                    ..constraints.clone()
+                },
+            )
+        };
+        let (Bound::Included(bound) | Bound::Excluded(bound)) = bound else {
+            return None;
+        };
+        let sign = if below { -1.0 } else { 1.0 };
+        let candidates = [
+            bound,
+            bound + sign,
+            if below {
+                bound.floor() - 1.0
+            } else {
+                bound.ceil() + 1.0
+            },
+            bound + sign * bound.abs() / 2.0,
+            bound + sign * bound.abs() * 2.0,
+        ]
+        .into_iter()
+        .filter(|float| float.is_finite())
+        .map(|float| {
+            if float.fract() == 0.0 && (i64::MIN as f64..u64::MAX as f64).contains(&float) {
+                integer_number(float as i128)
+            } else {
+                InternalJsonNumber::Float { inner: float }
+            }
+        })
+        .filter(|number| unbounded.matches_number(number) && !constraints.matches_number(number))
+        .collect::<Vec<_>>();
+        (!candidates.is_empty()).then(|| candidates[self.rng.usize(..candidates.len())].clone())
+    }
+}
+
+impl Mutator<NearMiss> for InternalNearMissMutator {
+    type Cache = NearMissCache;
+    type MutationStep = ();
+    type ArbitraryStep = ();
+    type UnmutateToken = (NearMiss, NearMissCache);
+
+    fn default_arbitrary_step(&self) -> Self::ArbitraryStep {}
+
+    fn is_valid(&self, value: &NearMiss) -> bool {
+        self.validate_value(value).is_some()
+    }
+
+    fn validate_value(&self, value: &NearMiss) -> Option<Self::Cache> {
+        if self.shape().matches(self.shape().root(), &value.broken) {
+            return None;
+        }
+        let valid = self.repair(value)?;
+        Some(NearMissCache {
+            valid_cplx: self.inner.cplx(&valid),
+            valid,
+            cplx: self.inner.cplx(&value.broken),
+        })
+    }
+
+    fn default_mutation_step(&self, _value: &NearMiss, _cache: &Self::Cache) -> Self::MutationStep {
+    }
+
+    fn global_search_space_complexity(&self) -> f64 {
+        f64::INFINITY
+    }
+
+    fn max_complexity(&self) -> f64 {
+        f64::INFINITY
+    }
+
+    fn min_complexity(&self) -> f64 {
+        // a near miss may be simpler than every valid value, but not simpler
+        // than `0`
+        self.inner.cplx(&InternalJsonValue::Number {
+            inner: integer_number(0),
+        })
+    }
+
+    fn complexity(&self, _value: &NearMiss, cache: &Self::Cache) -> f64 {
+        cache.cplx
+    }
+
+    fn ordered_arbitrary(
+        &self,
+        _step: &mut Self::ArbitraryStep,
+        max_cplx: f64,
+    ) -> Option<(NearMiss, f64)> {
+        (0..MAX_VIOLATION_ATTEMPTS).find_map(|_| {
+            let (valid, cplx) = self.inner.random_arbitrary(max_cplx);
+            let near_miss = self.violate(&valid, max_cplx - cplx)?;
+            let cplx = self.inner.cplx(&near_miss.broken);
+            Some((near_miss, cplx))
+        })
+    }
+
+    fn random_arbitrary(&self, max_cplx: f64) -> (NearMiss, f64) {
+        self.ordered_arbitrary(&mut (), max_cplx)
+            .or_else(|| self.fallback.clone())
+            .expect("the fallback is found when the mutator is built")
+    }
+
+    fn ordered_mutate(
+        &self,
+        value: &mut NearMiss,
+        cache: &mut Self::Cache,
+        _step: &mut Self::MutationStep,
+        _subvalue_provider: &dyn SubValueProvider,
+        max_cplx: f64,
+    ) -> Option<(Self::UnmutateToken, f64)> {
+        Some(self.random_mutate(value, cache, max_cplx))
+    }
+
+    /// Either breaks another constraint of the valid value, or mutates the
+    /// valid value and then breaks one of its constraints.
+    fn random_mutate(
+        &self,
+        value: &mut NearMiss,
+        cache: &mut Self::Cache,
+        max_cplx: f64,
+    ) -> (Self::UnmutateToken, f64) {
+        let token = (value.clone(), cache.clone());
+        for _ in 0..MAX_VIOLATION_ATTEMPTS {
+            let mut valid = cache.valid.clone();
+            let mut valid_cplx = cache.valid_cplx;
+            if self.rng.bool() {
+                self.inner
+                    .random_mutate(&mut valid, &mut valid_cplx, max_cplx);
+            }
+            if let Some(near_miss) = self.violate(&valid, max_cplx - valid_cplx) {
+                let cplx = self.inner.cplx(&near_miss.broken);
+                if cplx <= max_cplx {
+                    *value = near_miss;
+                    *cache = NearMissCache {
+                        valid,
+                        valid_cplx,
+                        cplx,
+                    };
+                    break;
+                }
+            }
+        }
+        (token, cache.cplx)
+    }
+
+    fn unmutate(&self, value: &mut NearMiss, cache: &mut Self::Cache, t: Self::UnmutateToken) {
+        (*value, *cache) = t;
+    }
+
+    fn visit_subvalues<'a>(
+        &self,
+        _value: &'a NearMiss,
+        cache: &'a Self::Cache,
+        visit: &mut dyn FnMut(&'a dyn Any, f64),
+    ) {
+        self.inner
+            .visit_subvalues(&cache.valid, &cache.valid_cplx, visit);
+    }
+}
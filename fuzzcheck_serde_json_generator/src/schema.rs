@@ -0,0 +1,616 @@
+//! Compiles a JSON Schema into a [`Shape`].
+
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Bound;
+
+use serde_json::{Map, Value};
+
+use crate::shape::{Constraints, Shape, ShapeBuilder, ShapeId, ShapeNode, ANY};
+use crate::{map_serde_json_to_internal, ValueKinds};
+
+/// The keywords which are compiled (see
+/// [`json_schema_mutator`](crate::json_schema_mutator)), apart from `$ref`.
+const KEYWORDS: [&str; 17] = [
+    "type",
+    "enum",
+    "const",
+    "minimum",
+    "maximum",
+    "exclusiveMinimum",
+    "exclusiveMaximum",
+    "minLength",
+    "maxLength",
+    "items",
+    "minItems",
+    "maxItems",
+    "properties",
+    "required",
+    "additionalProperties",
+    "oneOf",
+    "anyOf",
+];
+
+/// How many `$ref`s are followed when merging the branches of `oneOf` and
+/// `anyOf` with their sibling keywords, before deciding that they form a
+/// cycle.
+const MAX_REFERENCE_CHAIN: usize = 32;
+
+/// The reason why [`json_schema_mutator`](crate::json_schema_mutator) could
+/// not build a mutator from a schema.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SchemaError {
+    /// A (sub)schema is neither an object nor a boolean.
+    InvalidSchema { pointer: String },
+    /// The value of a keyword has the wrong type, e.g. a negative `minLength`
+    /// or an unknown `type`.
+    InvalidKeyword { pointer: String, keyword: String },
+    /// A keyword is used in a way which isn't supported, e.g. `items` given
+    /// as an array, or an `anyOf` both next to a `oneOf` and in one of its
+    /// branches.
+    UnsupportedKeyword { pointer: String, keyword: String },
+    /// A `$ref` is not a JSON pointer into the schema document itself (such as
+    /// `#/$defs/node`), or it doesn't point to anything.
+    UnresolvableReference(String),
+    /// No value matches the schema, or every value which does is infinitely
+    /// nested.
+    Unsatisfiable,
+    /// The schema doesn't have any constraint which a value can violate on its
+    /// own (see
+    /// [`json_schema_near_miss_mutator`](crate::json_schema_near_miss_mutator)).
+    NothingToViolate,
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidSchema { pointer } => {
+                write!(
+                    f,
+                    "the schema at `#{pointer}` is not an object or a boolean"
+                )
+            }
+            Self::InvalidKeyword { pointer, keyword } => {
+                write!(f, "the value of `{keyword}` at `#{pointer}` is invalid")
+            }
+            Self::UnsupportedKeyword { pointer, keyword } => {
+                write!(
+                    f,
+                    "the form of `{keyword}` at `#{pointer}` is not supported"
+                )
+            }
+            Self::UnresolvableReference(reference) => {
+                write!(f, "the reference `{reference}` cannot be resolved")
+            }
+            Self::Unsatisfiable => write!(f, "no value matches the schema"),
+            Self::NothingToViolate => {
+                write!(
+                    f,
+                    "the schema has no constraint which can be violated on its own"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+/// Compiles `document` into a shape whose root node is the whole document.
+pub(crate) fn compile(document: &Value) -> Result<Shape, SchemaError> {
+    let mut compiler = Compiler {
+        document,
+        builder: ShapeBuilder::new(),
+        references: HashMap::new(),
+    };
+    let root = compiler.compile_reference("#", &Map::new())?;
+    let mut shape = compiler.builder.build(root);
+    shape.restrict_enums();
+    if shape.min_complexity(root).is_infinite() {
+        return Err(SchemaError::Unsatisfiable);
+    }
+    Ok(shape)
+}
+
+struct Compiler<'a> {
+    document: &'a Value,
+    builder: ShapeBuilder,
+    /// The node of every `$ref` compiled so far.
+    references: HashMap<String, ShapeId>,
+}
+
+impl<'a> Compiler<'a> {
+    fn resolve(&self, reference: &str) -> Result<&'a Value, SchemaError> {
+        let unresolvable = || SchemaError::UnresolvableReference(reference.to_owned());
+        let pointer = reference.strip_prefix('#').ok_or_else(unresolvable)?;
+        self.document.pointer(pointer).ok_or_else(unresolvable)
+    }
+
+    /// Compiles the schema `reference` points to, combined with the keywords
+    /// next to the `$ref`.
+    fn compile_reference(
+        &mut self,
+        reference: &str,
+        siblings: &Map<String, Value>,
+    ) -> Result<ShapeId, SchemaError> {
+        // a `$ref` with siblings may also appear inside the schema it refers
+        // to, so it is compiled only once too
+        let key = if siblings.is_empty() {
+            reference.to_owned()
+        } else {
+            format!("{reference} {}", Value::Object(siblings.clone()))
+        };
+        if let Some(id) = self.references.get(&key) {
+            return Ok(*id);
+        }
+        let schema = self.resolve(reference)?;
+        // the node is added before compiling the schema, which may refer to
+        // itself
+        let id = self.builder.add(ShapeNode::AnyOf(Vec::new()));
+        self.references.insert(key, id);
+        let target = if siblings.is_empty() {
+            self.compile(schema, &reference[1..])?
+        } else {
+            let merged = self.merge(siblings, schema, &reference[1..])?;
+            self.compile(&merged, &reference[1..])?
+        };
+        self.builder.replace(id, ShapeNode::AnyOf(vec![target]));
+        Ok(id)
+    }
+
+    fn compile(&mut self, schema: &Value, pointer: &str) -> Result<ShapeId, SchemaError> {
+        let schema = match schema {
+            Value::Bool(true) => return Ok(ANY),
+            Value::Bool(false) => return Ok(self.builder.add(ShapeNode::AnyOf(Vec::new()))),
+            Value::Object(schema) => schema,
+            _ => {
+                return Err(SchemaError::InvalidSchema {
+                    pointer: pointer.to_owned(),
+                })
+            }
+        };
+        let invalid = |keyword: &str| SchemaError::InvalidKeyword {
+            pointer: pointer.to_owned(),
+            keyword: keyword.to_owned(),
+        };
+
+        // the keywords next to `$ref` apply along with the referenced schema
+        // (as they do since draft 2019-09)
+        if let Some(reference) = schema.get("$ref") {
+            let reference = reference.as_str().ok_or_else(|| invalid("$ref"))?;
+            let siblings = schema
+                .iter()
+                .filter(|(keyword, _)| KEYWORDS.contains(&keyword.as_str()))
+                .map(|(keyword, value)| (keyword.clone(), value.clone()))
+                .collect();
+            return self.compile_reference(reference, &siblings);
+        }
+        // the keywords next to `oneOf` and `anyOf` are merged into each branch
+        for (keyword, is_one_of) in [("oneOf", true), ("anyOf", false)] {
+            if let Some(branches) = schema.get(keyword) {
+                let branches = branches.as_array().ok_or_else(|| invalid(keyword))?;
+                let mut siblings = schema.clone();
+                siblings.remove(keyword);
+                let ids = branches
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, branch)| {
+                        let pointer = format!("{pointer}/{keyword}/{idx}");
+                        let merged = self.merge(&siblings, branch, &pointer)?;
+                        self.compile(&merged, &pointer)
+                    })
+                    .collect::<Result<_, _>>()?;
+                let node = if is_one_of {
+                    ShapeNode::OneOf(ids)
+                } else {
+                    ShapeNode::AnyOf(ids)
+                };
+                return Ok(self.builder.add(node));
+            }
+        }
+
+        let constraints = self.compile_constraints(schema, pointer)?;
+        let constraints = self.builder.add(ShapeNode::Constraints(constraints));
+        // a value must be both the `const` and one of the `enum` values
+        let values = match (schema.get("const"), schema.get("enum")) {
+            (Some(value), None) => vec![value.clone()],
+            (Some(value), Some(Value::Array(values))) => values
+                .iter()
+                .filter(|other| *other == value)
+                .cloned()
+                .collect(),
+            (None, Some(Value::Array(values))) => values.clone(),
+            (_, Some(_)) => return Err(invalid("enum")),
+            (None, None) => return Ok(constraints),
+        };
+        Ok(self.builder.add(ShapeNode::Enum {
+            values: values.into_iter().map(map_serde_json_to_internal).collect(),
+            constraints,
+        }))
+    }
+
+    fn compile_constraints(
+        &mut self,
+        schema: &Map<String, Value>,
+        pointer: &str,
+    ) -> Result<Constraints, SchemaError> {
+        let invalid = |keyword: &str| SchemaError::InvalidKeyword {
+            pointer: pointer.to_owned(),
+            keyword: keyword.to_owned(),
+        };
+        let number = |keyword: &str| match schema.get(keyword) {
+            Some(value) => value.as_f64().map(Some).ok_or_else(|| invalid(keyword)),
+            None => Ok(None),
+        };
+        // a length may be written as a float, as long as it is an integer
+        // (e.g. `2.0`)
+        let length = |keyword: &str| match schema.get(keyword) {
+            Some(value) => value
+                .as_u64()
+                .or_else(|| {
+                    value
+                        .as_f64()
+                        .filter(|length| *length >= 0.0 && length.fract() == 0.0)
+                        .map(|length| length as u64)
+                })
+                .map(|length| Some(length.try_into().unwrap_or(usize::MAX)))
+                .ok_or_else(|| invalid(keyword)),
+            None => Ok(None),
+        };
+
+        let mut constraints = Constraints::of_kinds(ValueKinds::ALL, ANY, ANY);
+
+        if let Some(types) = schema.get("type") {
+            let types = match types {
+                Value::String(name) => vec![name.as_str()],
+                Value::Array(names) => names
+                    .iter()
+                    .map(|name| name.as_str().ok_or_else(|| invalid("type")))
+                    .collect::<Result<_, _>>()?,
+                _ => return Err(invalid("type")),
+            };
+            constraints.kinds = ValueKinds::NONE;
+            for name in &types {
+                constraints.kinds = constraints.kinds
+                    | match *name {
+                        "null" => ValueKinds::NULL,
+                        "boolean" => ValueKinds::BOOL,
+                        "integer" | "number" => ValueKinds::NUMBER,
+                        "string" => ValueKinds::STRING,
+                        "array" => ValueKinds::ARRAY,
+                        "object" => ValueKinds::OBJECT,
+                        _ => return Err(invalid("type")),
+                    };
+            }
+            constraints.integer = types.contains(&"integer") && !types.contains(&"number");
+        }
+
+        if let Some(minimum) = number("minimum")? {
+            constraints.minimum = Bound::Included(minimum);
+        }
+        if let Some(maximum) = number("maximum")? {
+            constraints.maximum = Bound::Included(maximum);
+        }
+        // draft 4 makes `minimum` and `maximum` exclusive with a boolean,
+        // later drafts give the exclusive bound directly
+        match schema.get("exclusiveMinimum") {
+            Some(Value::Bool(true)) => {
+                if let Bound::Included(minimum) = constraints.minimum {
+                    constraints.minimum = Bound::Excluded(minimum);
+                }
+            }
+            Some(Value::Bool(false)) | None => {}
+            Some(_) => {
+                let minimum = number("exclusiveMinimum")?.unwrap_or(f64::NEG_INFINITY);
+                match constraints.minimum {
+                    Bound::Included(included) if included > minimum => {}
+                    _ => constraints.minimum = Bound::Excluded(minimum),
+                }
+            }
+        }
+        match schema.get("exclusiveMaximum") {
+            Some(Value::Bool(true)) => {
+                if let Bound::Included(maximum) = constraints.maximum {
+                    constraints.maximum = Bound::Excluded(maximum);
+                }
+            }
+            Some(Value::Bool(false)) | None => {}
+            Some(_) => {
+                let maximum = number("exclusiveMaximum")?.unwrap_or(f64::INFINITY);
+                match constraints.maximum {
+                    Bound::Included(included) if included < maximum => {}
+                    _ => constraints.maximum = Bound::Excluded(maximum),
+                }
+            }
+        }
+
+        if let Some(min_length) = length("minLength")? {
+            constraints.min_length = min_length;
+        }
+        if let Some(max_length) = length("maxLength")? {
+            constraints.max_length = max_length;
+        }
+        if let Some(min_items) = length("minItems")? {
+            constraints.min_items = min_items;
+        }
+        if let Some(max_items) = length("maxItems")? {
+            constraints.max_items = max_items;
+        }
+
+        match schema.get("items") {
+            Some(Value::Array(_)) => {
+                return Err(SchemaError::UnsupportedKeyword {
+                    pointer: pointer.to_owned(),
+                    keyword: "items".to_owned(),
+                })
+            }
+            Some(items) => constraints.items = self.compile(items, &format!("{pointer}/items"))?,
+            None => {}
+        }
+
+        match schema.get("properties") {
+            Some(Value::Object(properties)) => {
+                for (key, property) in properties {
+                    let property_pointer = format!(
+                        "{pointer}/properties/{}",
+                        key.replace('~', "~0").replace('/', "~1")
+                    );
+                    let id = self.compile(property, &property_pointer)?;
+                    constraints.properties.push((key.clone(), id));
+                }
+            }
+            Some(_) => return Err(invalid("properties")),
+            None => {}
+        }
+        match schema.get("required") {
+            Some(Value::Array(required)) => {
+                constraints.required = required
+                    .iter()
+                    .map(|key| {
+                        key.as_str()
+                            .map(str::to_owned)
+                            .ok_or_else(|| invalid("required"))
+                    })
+                    .collect::<Result<_, _>>()?;
+            }
+            Some(_) => return Err(invalid("required")),
+            None => {}
+        }
+        match schema.get("additionalProperties") {
+            Some(Value::Bool(false)) => constraints.additional_properties = None,
+            Some(additional_properties) => {
+                constraints.additional_properties = Some(self.compile(
+                    additional_properties,
+                    &format!("{pointer}/additionalProperties"),
+                )?)
+            }
+            None => {}
+        }
+
+        Ok(constraints)
+    }
+
+    /// Combines `branch` (e.g. a branch of a `oneOf`) with `siblings`, the
+    /// keywords next to it, into a schema matching the values which match
+    /// both. The `$ref`s of `branch` are followed, and combined with the
+    /// keywords next to them.
+    fn merge(
+        &self,
+        siblings: &Map<String, Value>,
+        branch: &Value,
+        pointer: &str,
+    ) -> Result<Value, SchemaError> {
+        let mut merged = siblings.clone();
+        let mut branch = branch;
+        for _ in 0..MAX_REFERENCE_CHAIN {
+            let (reference, schema) = match (branch.get("$ref").and_then(Value::as_str), branch) {
+                (Some(reference), Value::Object(schema)) => (reference, schema),
+                _ => break,
+            };
+            let mut schema = schema.clone();
+            schema.remove("$ref");
+            merged = self.combine(&merged, &schema, pointer)?;
+            branch = self.resolve(reference)?;
+        }
+        match branch {
+            Value::Bool(true) => Ok(Value::Object(merged)),
+            Value::Object(branch) if !branch.contains_key("$ref") => {
+                Ok(Value::Object(self.combine(&merged, branch, pointer)?))
+            }
+            Value::Object(_) => Err(SchemaError::UnresolvableReference(
+                branch["$ref"].to_string(),
+            )),
+            _ => Ok(branch.clone()),
+        }
+    }
+
+    /// Combines the keywords of two schemas: the tighter of their bounds, the
+    /// types and `enum` values they have in common, all of their `required`
+    /// members, and the combination of their subschemas. Of the keywords which
+    /// aren't supported (such as `pattern` or `format`), those of `first` are
+    /// kept.
+    fn combine(
+        &self,
+        first: &Map<String, Value>,
+        second: &Map<String, Value>,
+        pointer: &str,
+    ) -> Result<Map<String, Value>, SchemaError> {
+        let invalid = |keyword: &str| SchemaError::InvalidKeyword {
+            pointer: pointer.to_owned(),
+            keyword: keyword.to_owned(),
+        };
+        let unsupported = |keyword: &str| SchemaError::UnsupportedKeyword {
+            pointer: pointer.to_owned(),
+            keyword: keyword.to_owned(),
+        };
+        let (first, second) = (normalize(first), normalize(second));
+
+        // `additionalProperties` applies to the members which aren't in the
+        // `properties` next to it, which must then be the same in both
+        let declared = |schema: &Map<String, Value>| {
+            schema
+                .get("properties")
+                .and_then(Value::as_object)
+                .map(|properties| properties.keys().cloned().collect::<Vec<_>>())
+                .unwrap_or_default()
+        };
+        for (schema, other) in [(&first, &second), (&second, &first)] {
+            if schema
+                .get("additionalProperties")
+                .is_some_and(|additional| *additional != Value::Bool(true))
+                && declared(other)
+                    .iter()
+                    .any(|key| !declared(schema).contains(key))
+            {
+                return Err(unsupported("additionalProperties"));
+            }
+        }
+
+        let mut merged = first.clone();
+        for (keyword, value) in &second {
+            let existing = match merged.get(keyword) {
+                Some(existing) if existing != value => existing,
+                _ => {
+                    merged.insert(keyword.clone(), value.clone());
+                    continue;
+                }
+            };
+            let tighter = |pick: fn(f64, f64) -> f64| {
+                let (a, b) = (
+                    existing.as_f64().ok_or_else(|| invalid(keyword))?,
+                    value.as_f64().ok_or_else(|| invalid(keyword))?,
+                );
+                Ok(if pick(a, b) == a { existing } else { value }.clone())
+            };
+            let combined = match keyword.as_str() {
+                "minimum" | "exclusiveMinimum" | "minLength" | "minItems" => tighter(f64::max)?,
+                "maximum" | "exclusiveMaximum" | "maxLength" | "maxItems" => tighter(f64::min)?,
+                "type" => {
+                    let names = |types: &Value| match types {
+                        Value::String(name) => Ok(vec![name.clone()]),
+                        Value::Array(names) => names
+                            .iter()
+                            .map(|name| {
+                                name.as_str()
+                                    .map(str::to_owned)
+                                    .ok_or_else(|| invalid("type"))
+                            })
+                            .collect(),
+                        _ => Err(invalid("type")),
+                    };
+                    let (a, b) = (names(existing)?, names(value)?);
+                    let has =
+                        |names: &[String], name: &str| names.iter().any(|other| other == name);
+                    let mut types = Vec::new();
+                    for name in &a {
+                        let common = if has(&b, name) {
+                            name.as_str()
+                        } else if (name == "integer" && has(&b, "number"))
+                            || (name == "number" && has(&b, "integer"))
+                        {
+                            "integer"
+                        } else {
+                            continue;
+                        };
+                        if !has(&types, common) {
+                            types.push(common.to_owned());
+                        }
+                    }
+                    Value::Array(types.into_iter().map(Value::String).collect())
+                }
+                "enum" => match (existing, value) {
+                    (Value::Array(a), Value::Array(b)) => Value::Array(
+                        a.iter()
+                            .filter(|value| b.contains(value))
+                            .cloned()
+                            .collect(),
+                    ),
+                    _ => return Err(invalid("enum")),
+                },
+                "required" => match (existing, value) {
+                    (Value::Array(a), Value::Array(b)) => Value::Array(
+                        a.iter()
+                            .chain(b.iter().filter(|key| !a.contains(key)))
+                            .cloned()
+                            .collect(),
+                    ),
+                    _ => return Err(invalid("required")),
+                },
+                "properties" => match (existing, value) {
+                    (Value::Object(a), Value::Object(b)) => {
+                        let mut properties = a.clone();
+                        for (key, property) in b {
+                            let combined = match a.get(key) {
+                                Some(other) => self.combine_schemas(other, property, pointer)?,
+                                None => property.clone(),
+                            };
+                            properties.insert(key.clone(), combined);
+                        }
+                        Value::Object(properties)
+                    }
+                    _ => return Err(invalid("properties")),
+                },
+                "items" | "additionalProperties" => {
+                    self.combine_schemas(existing, value, pointer)?
+                }
+                keyword if KEYWORDS.contains(&keyword) => return Err(unsupported(keyword)),
+                _ => continue,
+            };
+            merged.insert(keyword.clone(), combined);
+        }
+        Ok(merged)
+    }
+
+    /// Combines two subschemas, which may be booleans or have a `$ref`.
+    fn combine_schemas(
+        &self,
+        first: &Value,
+        second: &Value,
+        pointer: &str,
+    ) -> Result<Value, SchemaError> {
+        match first {
+            // a `$ref` in `first` is kept, and compiled with the rest
+            Value::Object(first) => self.merge(first, second, pointer),
+            Value::Bool(true) => Ok(second.clone()),
+            _ => Ok(first.clone()),
+        }
+    }
+}
+
+/// Rewrites the keywords of `schema` which can't be combined with those of
+/// another schema as they are: the boolean `exclusiveMinimum` and
+/// `exclusiveMaximum` of draft 4 become the exclusive bounds of later drafts,
+/// and `const` becomes an `enum`.
+fn normalize(schema: &Map<String, Value>) -> Map<String, Value> {
+    let mut schema = schema.clone();
+    for (bound, exclusive) in [
+        ("minimum", "exclusiveMinimum"),
+        ("maximum", "exclusiveMaximum"),
+    ] {
+        match schema.get(exclusive) {
+            Some(Value::Bool(true)) => match schema.remove(bound) {
+                Some(bound) => {
+                    schema.insert(exclusive.to_owned(), bound);
+                }
+                None => {
+                    schema.remove(exclusive);
+                }
+            },
+            Some(Value::Bool(false)) => {
+                schema.remove(exclusive);
+            }
+            _ => {}
+        }
+    }
+    if let Some(value) = schema.remove("const") {
+        let values = match schema.get("enum") {
+            Some(Value::Array(values)) => values
+                .iter()
+                .filter(|other| **other == value)
+                .cloned()
+                .collect(),
+            _ => vec![value],
+        };
+        schema.insert("enum".to_owned(), Value::Array(values));
+    }
+    schema
+}
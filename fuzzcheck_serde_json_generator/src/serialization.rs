@@ -0,0 +1,132 @@
+//! Serializes and deserializes the internal representation directly, rather
+//! than through a [`serde_json::Value`], so that the members of objects keep
+//! their order and duplicate keys are preserved.
+
+use std::fmt;
+
+use serde::de::{self, MapAccess, SeqAccess, Visitor};
+use serde::ser::{SerializeMap, SerializeSeq};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+#[cfg(feature = "arbitrary_precision")]
+use crate::{map_internal_number_to_serde, map_serde_json_number_to_internal};
+use crate::{InternalJsonNumber, InternalJsonValue};
+
+/// The key of the map through which `serde_json` hands over the literal of a
+/// number when its `arbitrary_precision` feature is enabled. This means that
+/// an object with this single key is read as a number, as it is by
+/// `serde_json` itself.
+#[cfg(feature = "arbitrary_precision")]
+const NUMBER_TOKEN: &str = "$serde_json::private::Number";
+
+impl Serialize for InternalJsonValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            InternalJsonValue::Null => serializer.serialize_unit(),
+            InternalJsonValue::Bool { inner } => serializer.serialize_bool(*inner),
+            InternalJsonValue::Number { inner } => match inner {
+                InternalJsonNumber::PosInt { inner } => serializer.serialize_u64(*inner),
+                InternalJsonNumber::NegInt { inner } => serializer.serialize_i64(*inner),
+                InternalJsonNumber::Float { inner } => serializer.serialize_f64(*inner),
+                #[cfg(feature = "arbitrary_precision")]
+                InternalJsonNumber::Decimal { .. } => {
+                    map_internal_number_to_serde(inner.clone()).serialize(serializer)
+                }
+            },
+            InternalJsonValue::String { inner } => serializer.serialize_str(inner),
+            InternalJsonValue::Array { inner } => {
+                let mut seq = serializer.serialize_seq(Some(inner.len()))?;
+                for element in inner {
+                    seq.serialize_element(element)?;
+                }
+                seq.end()
+            }
+            InternalJsonValue::Object { inner } => {
+                let mut map = serializer.serialize_map(Some(inner.len()))?;
+                for (key, value) in inner {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for InternalJsonValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(InternalJsonValueVisitor)
+    }
+}
+
+struct InternalJsonValueVisitor;
+
+impl<'de> Visitor<'de> for InternalJsonValueVisitor {
+    type Value = InternalJsonValue;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("any JSON value")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(InternalJsonValue::Null)
+    }
+
+    fn visit_bool<E: de::Error>(self, inner: bool) -> Result<Self::Value, E> {
+        Ok(InternalJsonValue::Bool { inner })
+    }
+
+    fn visit_u64<E: de::Error>(self, inner: u64) -> Result<Self::Value, E> {
+        Ok(InternalJsonValue::Number {
+            inner: InternalJsonNumber::PosInt { inner },
+        })
+    }
+
+    fn visit_i64<E: de::Error>(self, inner: i64) -> Result<Self::Value, E> {
+        // non-negative integers are always `PosInt`s, as in a
+        // `serde_json::Number`
+        match u64::try_from(inner) {
+            Ok(inner) => self.visit_u64(inner),
+            Err(_) => Ok(InternalJsonValue::Number {
+                inner: InternalJsonNumber::NegInt { inner },
+            }),
+        }
+    }
+
+    fn visit_f64<E: de::Error>(self, inner: f64) -> Result<Self::Value, E> {
+        Ok(InternalJsonValue::Number {
+            inner: InternalJsonNumber::Float { inner },
+        })
+    }
+
+    fn visit_str<E: de::Error>(self, inner: &str) -> Result<Self::Value, E> {
+        self.visit_string(inner.to_owned())
+    }
+
+    fn visit_string<E: de::Error>(self, inner: String) -> Result<Self::Value, E> {
+        Ok(InternalJsonValue::String { inner })
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut inner = Vec::new();
+        while let Some(element) = seq.next_element()? {
+            inner.push(element);
+        }
+        Ok(InternalJsonValue::Array { inner })
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut inner = Vec::new();
+        while let Some(key) = map.next_key::<String>()? {
+            #[cfg(feature = "arbitrary_precision")]
+            if inner.is_empty() && key == NUMBER_TOKEN {
+                let literal = map.next_value::<String>()?;
+                let number = literal.parse().map_err(de::Error::custom)?;
+                return Ok(InternalJsonValue::Number {
+                    inner: map_serde_json_number_to_internal(&number),
+                });
+            }
+            inner.push((key, map.next_value()?));
+        }
+        Ok(InternalJsonValue::Object { inner })
+    }
+}
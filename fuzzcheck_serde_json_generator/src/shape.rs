@@ -0,0 +1,505 @@
+//! A description of the values which may be placed at each position of a JSON
+//! document. The value mutator is driven by a [`Shape`], which is built from a
+//! [`JsonValueMutatorConfig`], from a JSON Schema (see
+//! [`schema`](crate::schema)) or from examples (see [`infer`](crate::infer)).
+
+use std::ops::{Bound, RangeBounds};
+
+use crate::{
+    ComplexityModel, InternalJsonNumber, InternalJsonValue, JsonValueMutatorConfig, ValueKinds,
+};
+
+/// Identifies a node of a [`Shape`].
+pub(crate) type ShapeId = usize;
+
+/// The node matching every value, which is always present in a [`Shape`].
+pub(crate) const ANY: ShapeId = 0;
+
+#[derive(Clone)]
+pub(crate) enum ShapeNode {
+    /// Values which satisfy all of the constraints.
+    Constraints(Constraints),
+    /// One of a fixed set of values, which also match the node `constraints`
+    /// (i.e. the other keywords of the schema containing the `enum`).
+    Enum {
+        values: Vec<InternalJsonValue>,
+        constraints: ShapeId,
+    },
+    /// Values which match at least one of the nodes.
+    AnyOf(Vec<ShapeId>),
+    /// Values which match exactly one of the nodes.
+    OneOf(Vec<ShapeId>),
+    /// Any value, although values matching the node `expected` are generated
+    /// with the given probability (and arbitrary values otherwise).
+    Usually { expected: ShapeId, probability: f64 },
+}
+
+#[derive(Clone)]
+pub(crate) struct Constraints {
+    pub(crate) kinds: ValueKinds,
+    /// Whether numbers must be integers (although they may be written as
+    /// floats, e.g. `1.0`).
+    pub(crate) integer: bool,
+    pub(crate) minimum: Bound<f64>,
+    pub(crate) maximum: Bound<f64>,
+    /// The bounds on the number of `char`s in a string.
+    pub(crate) min_length: usize,
+    pub(crate) max_length: usize,
+    pub(crate) items: ShapeId,
+    pub(crate) min_items: usize,
+    pub(crate) max_items: usize,
+    pub(crate) properties: Vec<(String, ShapeId)>,
+    pub(crate) required: Vec<String>,
+    /// The shape of the members whose key is not in `properties`, or `None` if
+    /// there may not be any.
+    pub(crate) additional_properties: Option<ShapeId>,
+}
+
+impl Constraints {
+    /// Constraints which only restrict the kind of a value, and whose array
+    /// elements and object members have the given shapes.
+    pub(crate) fn of_kinds(kinds: ValueKinds, items: ShapeId, members: ShapeId) -> Self {
+        Self {
+            kinds,
+            integer: false,
+            minimum: Bound::Unbounded,
+            maximum: Bound::Unbounded,
+            min_length: 0,
+            max_length: usize::MAX,
+            items,
+            min_items: 0,
+            max_items: usize::MAX,
+            properties: Vec::new(),
+            required: Vec::new(),
+            additional_properties: Some(members),
+        }
+    }
+
+    pub(crate) fn matches_number(&self, number: &InternalJsonNumber) -> bool {
+        let is_integer = match number {
+            InternalJsonNumber::Float { inner } => inner.fract() == 0.0,
+            #[cfg(feature = "arbitrary_precision")]
+            InternalJsonNumber::Decimal { .. } => as_f64(number).fract() == 0.0,
+            _ => true,
+        };
+        (!self.integer || is_integer) && (self.minimum, self.maximum).contains(&as_f64(number))
+    }
+
+    /// The smallest and largest integers which satisfy the bounds, if they are
+    /// representable by a [`serde_json::Number`].
+    pub(crate) fn integer_bounds(&self) -> (i128, i128) {
+        let clamp = |bound: f64| (bound as i128).clamp(i64::MIN as i128, u64::MAX as i128);
+        let lowest = match self.minimum {
+            Bound::Included(minimum) => clamp(minimum.ceil()),
+            Bound::Excluded(minimum) => clamp(minimum.floor() + 1.0),
+            Bound::Unbounded => i64::MIN as i128,
+        };
+        let highest = match self.maximum {
+            Bound::Included(maximum) => clamp(maximum.floor()),
+            Bound::Excluded(maximum) => clamp(maximum.ceil() - 1.0),
+            Bound::Unbounded => u64::MAX as i128,
+        };
+        (lowest, highest)
+    }
+
+    /// A number satisfying the constraints, preferably an integer close to 0.
+    /// Returns `None` if there isn't one.
+    pub(crate) fn simplest_number(&self) -> Option<InternalJsonNumber> {
+        let (lowest, highest) = self.integer_bounds();
+        let candidate = if lowest <= highest {
+            integer_number(0.clamp(lowest, highest))
+        } else {
+            let float = match (self.minimum, self.maximum) {
+                (Bound::Included(bound), _) | (_, Bound::Included(bound)) => bound,
+                (Bound::Excluded(minimum), Bound::Excluded(maximum)) => {
+                    minimum / 2.0 + maximum / 2.0
+                }
+                (Bound::Excluded(minimum), Bound::Unbounded) => minimum + minimum.abs().max(1.0),
+                (Bound::Unbounded, Bound::Excluded(maximum)) => maximum - maximum.abs().max(1.0),
+                (Bound::Unbounded, Bound::Unbounded) => 0.0,
+            };
+            InternalJsonNumber::Float { inner: float }
+        };
+        self.matches_number(&candidate).then_some(candidate)
+    }
+}
+
+pub(crate) fn kind_of(value: &InternalJsonValue) -> ValueKinds {
+    match value {
+        InternalJsonValue::Null => ValueKinds::NULL,
+        InternalJsonValue::Bool { .. } => ValueKinds::BOOL,
+        InternalJsonValue::Number { .. } => ValueKinds::NUMBER,
+        InternalJsonValue::String { .. } => ValueKinds::STRING,
+        InternalJsonValue::Array { .. } => ValueKinds::ARRAY,
+        InternalJsonValue::Object { .. } => ValueKinds::OBJECT,
+    }
+}
+
+pub(crate) fn as_f64(number: &InternalJsonNumber) -> f64 {
+    match *number {
+        InternalJsonNumber::PosInt { inner } => inner as f64,
+        InternalJsonNumber::NegInt { inner } => inner as f64,
+        InternalJsonNumber::Float { inner } => inner,
+        // the closest `f64`, which is infinite if the number is too large
+        #[cfg(feature = "arbitrary_precision")]
+        InternalJsonNumber::Decimal { ref inner } => inner
+            .parse()
+            .expect("a number literal is a valid `f64` literal"),
+    }
+}
+
+/// Converts an integer between `i64::MIN` and `u64::MAX` into a number.
+pub(crate) fn integer_number(integer: i128) -> InternalJsonNumber {
+    match u64::try_from(integer) {
+        Ok(inner) => InternalJsonNumber::PosInt { inner },
+        Err(_) => InternalJsonNumber::NegInt {
+            inner: integer as i64,
+        },
+    }
+}
+
+/// Equality as defined by JSON Schema: numbers are compared by value and the
+/// order of an object's members doesn't matter.
+fn json_eq(a: &InternalJsonValue, b: &InternalJsonValue) -> bool {
+    match (a, b) {
+        (InternalJsonValue::Null, InternalJsonValue::Null) => true,
+        (InternalJsonValue::Bool { inner: a }, InternalJsonValue::Bool { inner: b }) => a == b,
+        (InternalJsonValue::Number { inner: a }, InternalJsonValue::Number { inner: b }) => {
+            match (a, b) {
+                (InternalJsonNumber::Float { .. }, _) | (_, InternalJsonNumber::Float { .. }) => {
+                    as_f64(a) == as_f64(b)
+                }
+                #[cfg(feature = "arbitrary_precision")]
+                (InternalJsonNumber::Decimal { .. }, _)
+                | (_, InternalJsonNumber::Decimal { .. }) => as_f64(a) == as_f64(b),
+                (
+                    InternalJsonNumber::PosInt { inner: a },
+                    InternalJsonNumber::PosInt { inner: b },
+                ) => a == b,
+                (
+                    InternalJsonNumber::NegInt { inner: a },
+                    InternalJsonNumber::NegInt { inner: b },
+                ) => a == b,
+                _ => false,
+            }
+        }
+        (InternalJsonValue::String { inner: a }, InternalJsonValue::String { inner: b }) => a == b,
+        (InternalJsonValue::Array { inner: a }, InternalJsonValue::Array { inner: b }) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| json_eq(a, b))
+        }
+        (InternalJsonValue::Object { inner: a }, InternalJsonValue::Object { inner: b }) => {
+            a.len() == b.len()
+                && a.iter().all(|(key, a)| {
+                    b.iter()
+                        .any(|(other_key, b)| key == other_key && json_eq(a, b))
+                })
+        }
+        _ => false,
+    }
+}
+
+/// Accumulates the nodes of a [`Shape`], which may refer to each other (and
+/// to themselves).
+pub(crate) struct ShapeBuilder {
+    nodes: Vec<ShapeNode>,
+}
+
+impl ShapeBuilder {
+    pub(crate) fn new() -> Self {
+        Self {
+            nodes: vec![ShapeNode::Constraints(Constraints::of_kinds(
+                ValueKinds::ALL,
+                ANY,
+                ANY,
+            ))],
+        }
+    }
+
+    pub(crate) fn add(&mut self, node: ShapeNode) -> ShapeId {
+        self.nodes.push(node);
+        self.nodes.len() - 1
+    }
+
+    /// Replaces a node, which allows a node to be added before the nodes it
+    /// refers to.
+    pub(crate) fn replace(&mut self, id: ShapeId, node: ShapeNode) {
+        self.nodes[id] = node;
+    }
+
+    pub(crate) fn build(self, root: ShapeId) -> Shape {
+        let mut shape = Shape {
+            nodes: self.nodes,
+            root,
+            model: ComplexityModel::default(),
+            min_cplx: Vec::new(),
+        };
+        shape.compute_min_complexities();
+        shape
+    }
+}
+
+pub(crate) struct Shape {
+    nodes: Vec<ShapeNode>,
+    root: ShapeId,
+    /// How the complexities in `min_cplx` are measured.
+    model: ComplexityModel,
+    /// The complexity of the simplest value matching each node, which is
+    /// infinite if no (finite) value matches it.
+    min_cplx: Vec<f64>,
+}
+
+impl Shape {
+    /// The shape described by the kinds allowed at each position in `config`.
+    /// The limits in `config` are not part of the shape.
+    pub(crate) fn from_config(config: &JsonValueMutatorConfig) -> Self {
+        let mut builder = ShapeBuilder::new();
+        let element = builder.add(ShapeNode::AnyOf(Vec::new()));
+        let member = builder.add(ShapeNode::AnyOf(Vec::new()));
+        let node = |kinds| ShapeNode::Constraints(Constraints::of_kinds(kinds, element, member));
+        let root = builder.add(node(config.root_kinds));
+        builder.replace(element, node(config.array_element_kinds));
+        builder.replace(member, node(config.object_member_kinds));
+        builder.build(root)
+    }
+
+    pub(crate) fn root(&self) -> ShapeId {
+        self.root
+    }
+
+    pub(crate) fn node(&self, id: ShapeId) -> &ShapeNode {
+        &self.nodes[id]
+    }
+
+    pub(crate) fn min_complexity(&self, id: ShapeId) -> f64 {
+        self.min_cplx[id]
+    }
+
+    pub(crate) fn set_complexity_model(&mut self, model: ComplexityModel) {
+        if model != self.model {
+            self.model = model;
+            self.compute_min_complexities();
+        }
+    }
+
+    /// Removes the values of every [`ShapeNode::Enum`] which don't match its
+    /// `constraints`. This can only be done once all the nodes are known.
+    pub(crate) fn restrict_enums(&mut self) {
+        for id in 0..self.nodes.len() {
+            if let ShapeNode::Enum {
+                values,
+                constraints,
+            } = &self.nodes[id]
+            {
+                let values = values
+                    .iter()
+                    .filter(|value| self.matches(*constraints, value))
+                    .cloned()
+                    .collect();
+                let constraints = *constraints;
+                self.nodes[id] = ShapeNode::Enum {
+                    values,
+                    constraints,
+                };
+            }
+        }
+        self.compute_min_complexities();
+    }
+
+    /// The shape of the member with the given key in an object satisfying
+    /// `constraints`, or `None` if it may not have such a member.
+    pub(crate) fn member_shape(&self, constraints: &Constraints, key: &str) -> Option<ShapeId> {
+        constraints
+            .properties
+            .iter()
+            .find(|(property, _)| property == key)
+            .map(|(_, id)| *id)
+            .or(constraints.additional_properties)
+    }
+
+    pub(crate) fn matches(&self, id: ShapeId, value: &InternalJsonValue) -> bool {
+        self.matches_with(id, value, false)
+    }
+
+    /// The same as [`matches`](Self::matches), for `constraints` which aren't
+    /// necessarily those of a node of the shape.
+    pub(crate) fn matches_constraints(
+        &self,
+        constraints: &Constraints,
+        value: &InternalJsonValue,
+    ) -> bool {
+        self.satisfies(constraints, value, false)
+    }
+
+    /// The same as [`matches`](Self::matches), except that a value only
+    /// matches a [`ShapeNode::Usually`] node if it matches the node it
+    /// expects.
+    pub(crate) fn is_expected(&self, id: ShapeId, value: &InternalJsonValue) -> bool {
+        self.matches_with(id, value, true)
+    }
+
+    /// The probability with which a mutation may make a value stop matching
+    /// what the shape expects, or `None` if the shape has no expectations
+    /// beyond the values it matches.
+    pub(crate) fn deviation_probability(&self) -> Option<f64> {
+        match self.nodes[self.root] {
+            ShapeNode::Usually { probability, .. } => Some(1.0 - probability),
+            _ => None,
+        }
+    }
+
+    fn matches_with(&self, id: ShapeId, value: &InternalJsonValue, expected: bool) -> bool {
+        let matches = |id: &ShapeId| self.matches_with(*id, value, expected);
+        match &self.nodes[id] {
+            ShapeNode::Constraints(constraints) => self.satisfies(constraints, value, expected),
+            ShapeNode::Enum { values, .. } => values.iter().any(|other| json_eq(value, other)),
+            ShapeNode::AnyOf(ids) => ids.iter().any(matches),
+            ShapeNode::OneOf(ids) => ids.iter().filter(|id| matches(id)).count() == 1,
+            ShapeNode::Usually { expected: id, .. } => !expected || matches(id),
+        }
+    }
+
+    fn satisfies(
+        &self,
+        constraints: &Constraints,
+        value: &InternalJsonValue,
+        expected: bool,
+    ) -> bool {
+        constraints.kinds.contains(kind_of(value))
+            && match value {
+                InternalJsonValue::Null | InternalJsonValue::Bool { .. } => true,
+                InternalJsonValue::Number { inner } => constraints.matches_number(inner),
+                InternalJsonValue::String { inner } => (constraints.min_length
+                    ..=constraints.max_length)
+                    .contains(&inner.chars().count()),
+                InternalJsonValue::Array { inner } => {
+                    (constraints.min_items..=constraints.max_items).contains(&inner.len())
+                        && inner
+                            .iter()
+                            .all(|value| self.matches_with(constraints.items, value, expected))
+                }
+                InternalJsonValue::Object { inner } => {
+                    constraints
+                        .required
+                        .iter()
+                        .all(|required| inner.iter().any(|(key, _)| key == required))
+                        && inner.iter().all(|(key, value)| {
+                            self.member_shape(constraints, key)
+                                .is_some_and(|id| self.matches_with(id, value, expected))
+                        })
+                }
+            }
+    }
+
+    /// Follows the branches of `anyOf` and `oneOf` nodes which `value` matches,
+    /// until reaching another kind of node (or a node which `value` doesn't
+    /// match). A [`ShapeNode::Usually`] node resolves to the node it expects
+    /// if `value` matches it, and to [`ANY`] otherwise.
+    pub(crate) fn resolve(&self, mut id: ShapeId, value: &InternalJsonValue) -> ShapeId {
+        loop {
+            match &self.nodes[id] {
+                ShapeNode::AnyOf(ids) | ShapeNode::OneOf(ids) => {
+                    match ids.iter().find(|id| self.matches(**id, value)) {
+                        Some(branch) => id = *branch,
+                        None => return id,
+                    }
+                }
+                ShapeNode::Usually { expected, .. } if self.matches(*expected, value) => {
+                    id = *expected
+                }
+                ShapeNode::Usually { .. } => return ANY,
+                _ => return id,
+            }
+        }
+    }
+
+    /// The complexity of the simplest value of a single kind which satisfies
+    /// `constraints`.
+    pub(crate) fn kind_min_complexity(&self, constraints: &Constraints, kind: ValueKinds) -> f64 {
+        kind_min_complexity(self.model, &self.min_cplx, constraints, kind)
+    }
+
+    /// Computes the least fixed point of the minimum complexities, starting
+    /// from infinity everywhere so that nodes which only match infinitely
+    /// nested values keep an infinite complexity.
+    fn compute_min_complexities(&mut self) {
+        self.min_cplx = vec![f64::INFINITY; self.nodes.len()];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for id in 0..self.nodes.len() {
+                let min_cplx = match &self.nodes[id] {
+                    ShapeNode::Constraints(constraints) => constraints
+                        .kinds
+                        .iter()
+                        .map(|kind| {
+                            kind_min_complexity(self.model, &self.min_cplx, constraints, kind)
+                        })
+                        .fold(f64::INFINITY, f64::min),
+                    ShapeNode::Enum { values, .. } => values
+                        .iter()
+                        .map(|value| self.model.of_internal(value))
+                        .fold(f64::INFINITY, f64::min),
+                    ShapeNode::AnyOf(ids) | ShapeNode::OneOf(ids) => ids
+                        .iter()
+                        .map(|id| self.min_cplx[*id])
+                        .fold(f64::INFINITY, f64::min),
+                    ShapeNode::Usually { expected, .. } => {
+                        self.min_cplx[*expected].min(self.min_cplx[ANY])
+                    }
+                };
+                if min_cplx < self.min_cplx[id] {
+                    self.min_cplx[id] = min_cplx;
+                    changed = true;
+                }
+            }
+        }
+    }
+}
+
+fn kind_min_complexity(
+    model: ComplexityModel,
+    min_cplx: &[f64],
+    constraints: &Constraints,
+    kind: ValueKinds,
+) -> f64 {
+    match kind {
+        ValueKinds::NULL => model.of_internal(&InternalJsonValue::Null),
+        ValueKinds::BOOL => model.of_internal(&InternalJsonValue::Bool { inner: true }),
+        ValueKinds::NUMBER => constraints
+            .simplest_number()
+            .map_or(f64::INFINITY, |number| model.of_number(&number)),
+        ValueKinds::STRING if constraints.min_length <= constraints.max_length => {
+            model.of_string(&"a".repeat(constraints.min_length))
+        }
+        ValueKinds::ARRAY if constraints.min_items <= constraints.max_items => {
+            if constraints.min_items == 0 {
+                model.container(0.0, 0)
+            } else {
+                model.container(
+                    constraints.min_items as f64 * min_cplx[constraints.items],
+                    constraints.min_items,
+                )
+            }
+        }
+        ValueKinds::OBJECT => {
+            let mut required = constraints.required.clone();
+            required.sort();
+            required.dedup();
+            let members = required
+                .iter()
+                .map(|key| {
+                    let member_cplx = constraints
+                        .properties
+                        .iter()
+                        .find(|(property, _)| property == key)
+                        .map(|(_, id)| *id)
+                        .or(constraints.additional_properties)
+                        .map_or(f64::INFINITY, |id| min_cplx[id]);
+                    model.of_key(key) + member_cplx
+                })
+                .sum();
+            model.container(members, required.len())
+        }
+        _ => f64::INFINITY,
+    }
+}
@@ -0,0 +1,471 @@
+//! Wraps a mutator of [`serde_json::Value`] so that it only generates values
+//! which deserialize into a type, for
+//! [`json_typed_mutator`](crate::json_typed_mutator).
+//!
+//! Arbitrary values rarely deserialize into a struct with required fields, so
+//! a value is generated (or mutated) a bounded number of times, after which a
+//! fallback value is generated instead (or the value is left unchanged). The
+//! fallbacks are the examples the mutator was built with, and the simplest
+//! value of the type, which is built by deserializing it from a [`Sampler`].
+
+use std::any::Any;
+use std::marker::PhantomData;
+
+use fuzzcheck::fastrand::Rng;
+use fuzzcheck::{Mutator, SubValueProvider};
+use serde::de::value::Error;
+use serde::de::{
+    DeserializeOwned, DeserializeSeed, Deserializer, EnumAccess, Error as _, IntoDeserializer,
+    MapAccess, SeqAccess, VariantAccess, Visitor,
+};
+use serde::Serialize;
+use serde_json::Value;
+
+/// How many times a value is generated or mutated before giving up on finding
+/// one which deserializes.
+const MAX_DESERIALIZE_ATTEMPTS: usize = 64;
+
+/// How deeply the values built by a [`Sampler`] may be nested, which stops
+/// recursive types.
+const MAX_SAMPLE_DEPTH: usize = 32;
+
+pub(crate) struct DeserializingMutator<M, T> {
+    inner: M,
+    /// The fallback values (and their complexity) which are within the limits
+    /// of `inner` and deserialize, one of which is generated when none of the
+    /// values generated at random deserialize.
+    fallbacks: Vec<(Value, f64)>,
+    rng: Rng,
+    _phantom: PhantomData<fn() -> T>,
+}
+
+impl<M: Mutator<Value>, T: DeserializeOwned> DeserializingMutator<M, T> {
+    /// # Panics
+    ///
+    /// Panics if none of the `fallbacks` is a valid value of `inner` which
+    /// deserializes into a `T`.
+    pub(crate) fn new(inner: M, fallbacks: impl IntoIterator<Item = Value>) -> Self {
+        let fallbacks = fallbacks
+            .into_iter()
+            .filter(|example| T::deserialize(example).is_ok())
+            .filter_map(|example| {
+                let cache = inner.validate_value(&example)?;
+                let cplx = inner.complexity(&example, &cache);
+                Some((example, cplx))
+            })
+            .collect::<Vec<_>>();
+        assert!(
+            !fallbacks.is_empty(),
+            "json_typed_mutator couldn't build a value of the type within the limits of its \
+             configuration, use json_typed_mutator_from_examples to give it one"
+        );
+        Self {
+            inner,
+            fallbacks,
+            rng: Rng::new(),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Picks one of the fallbacks which is within `max_cplx`, or the simplest
+    /// one if none is.
+    fn fallback(&self, max_cplx: f64) -> (Value, f64) {
+        let within = self
+            .fallbacks
+            .iter()
+            .filter(|(_, cplx)| *cplx <= max_cplx)
+            .collect::<Vec<_>>();
+        if within.is_empty() {
+            self.fallbacks
+                .iter()
+                .min_by(|(_, a), (_, b)| a.total_cmp(b))
+                .expect("there is at least one fallback")
+                .clone()
+        } else {
+            within[self.rng.usize(..within.len())].clone()
+        }
+    }
+}
+
+impl<M: Mutator<Value>, T: DeserializeOwned + 'static> Mutator<Value>
+    for DeserializingMutator<M, T>
+{
+    type Cache = M::Cache;
+    type MutationStep = M::MutationStep;
+    type ArbitraryStep = M::ArbitraryStep;
+    /// `None` if no mutation of the value deserialized, in which case it was
+    /// left unchanged.
+    type UnmutateToken = Option<M::UnmutateToken>;
+
+    fn default_arbitrary_step(&self) -> Self::ArbitraryStep {
+        self.inner.default_arbitrary_step()
+    }
+
+    fn is_valid(&self, value: &Value) -> bool {
+        T::deserialize(value).is_ok() && self.inner.is_valid(value)
+    }
+
+    fn validate_value(&self, value: &Value) -> Option<Self::Cache> {
+        if T::deserialize(value).is_err() {
+            return None;
+        }
+        self.inner.validate_value(value)
+    }
+
+    fn default_mutation_step(&self, value: &Value, cache: &Self::Cache) -> Self::MutationStep {
+        self.inner.default_mutation_step(value, cache)
+    }
+
+    fn global_search_space_complexity(&self) -> f64 {
+        self.inner.global_search_space_complexity()
+    }
+
+    fn max_complexity(&self) -> f64 {
+        self.inner.max_complexity()
+    }
+
+    fn min_complexity(&self) -> f64 {
+        self.inner.min_complexity()
+    }
+
+    fn complexity(&self, value: &Value, cache: &Self::Cache) -> f64 {
+        self.inner.complexity(value, cache)
+    }
+
+    fn ordered_arbitrary(
+        &self,
+        step: &mut Self::ArbitraryStep,
+        max_cplx: f64,
+    ) -> Option<(Value, f64)> {
+        for _ in 0..MAX_DESERIALIZE_ATTEMPTS {
+            let (value, cplx) = self.inner.ordered_arbitrary(step, max_cplx)?;
+            if T::deserialize(&value).is_ok() {
+                return Some((value, cplx));
+            }
+        }
+        Some(self.fallback(max_cplx))
+    }
+
+    fn random_arbitrary(&self, max_cplx: f64) -> (Value, f64) {
+        (0..MAX_DESERIALIZE_ATTEMPTS)
+            .map(|_| self.inner.random_arbitrary(max_cplx))
+            .find(|(value, _)| T::deserialize(value).is_ok())
+            .unwrap_or_else(|| self.fallback(max_cplx))
+    }
+
+    fn ordered_mutate(
+        &self,
+        value: &mut Value,
+        cache: &mut Self::Cache,
+        step: &mut Self::MutationStep,
+        subvalue_provider: &dyn SubValueProvider,
+        max_cplx: f64,
+    ) -> Option<(Self::UnmutateToken, f64)> {
+        for _ in 0..MAX_DESERIALIZE_ATTEMPTS {
+            let (token, cplx) =
+                self.inner
+                    .ordered_mutate(value, cache, step, subvalue_provider, max_cplx)?;
+            if T::deserialize(&*value).is_ok() {
+                return Some((Some(token), cplx));
+            }
+            self.inner.unmutate(value, cache, token);
+        }
+        Some((None, self.inner.complexity(value, cache)))
+    }
+
+    fn random_mutate(
+        &self,
+        value: &mut Value,
+        cache: &mut Self::Cache,
+        max_cplx: f64,
+    ) -> (Self::UnmutateToken, f64) {
+        for _ in 0..MAX_DESERIALIZE_ATTEMPTS {
+            let (token, cplx) = self.inner.random_mutate(value, cache, max_cplx);
+            if T::deserialize(&*value).is_ok() {
+                return (Some(token), cplx);
+            }
+            self.inner.unmutate(value, cache, token);
+        }
+        (None, self.inner.complexity(value, cache))
+    }
+
+    fn unmutate(&self, value: &mut Value, cache: &mut Self::Cache, t: Self::UnmutateToken) {
+        if let Some(t) = t {
+            self.inner.unmutate(value, cache, t);
+        }
+    }
+
+    fn visit_subvalues<'a>(
+        &self,
+        value: &'a Value,
+        cache: &'a Self::Cache,
+        visit: &mut dyn FnMut(&'a dyn Any, f64),
+    ) {
+        self.inner.visit_subvalues(value, cache, visit);
+    }
+}
+
+/// The names of the fields and variants found while building the simplest
+/// value of a type, which are added to the key and value dictionaries.
+#[derive(Default)]
+pub(crate) struct Names {
+    pub(crate) fields: Vec<&'static str>,
+    pub(crate) variants: Vec<&'static str>,
+}
+
+/// Builds the simplest value of `T` serialized to JSON, or returns `None` if
+/// it doesn't deserialize from a [`Sampler`] (e.g. because it validates its
+/// fields). The names of the fields and variants are added to `names`.
+pub(crate) fn sample<T: Serialize + DeserializeOwned>(names: &mut Names) -> Option<Value> {
+    let value = T::deserialize(Sampler { depth: 0, names }).ok()?;
+    serde_json::to_value(value).ok()
+}
+
+/// A [`Deserializer`] which gives every type its simplest value: `false`, `0`,
+/// empty strings, sequences and maps, `None`, the first variant of enums and
+/// every field of structs. Self-describing types (whose deserialization
+/// relies on [`Deserializer::deserialize_any`]) get a unit.
+struct Sampler<'a> {
+    depth: usize,
+    names: &'a mut Names,
+}
+
+impl Sampler<'_> {
+    /// A sampler for the values inside the one this one is building.
+    fn nested(&mut self) -> Result<Sampler<'_>, Error> {
+        if self.depth == MAX_SAMPLE_DEPTH {
+            return Err(Error::custom("the type is nested too deeply"));
+        }
+        Ok(Sampler {
+            depth: self.depth + 1,
+            names: self.names,
+        })
+    }
+}
+
+macro_rules! sample_number {
+    ($($method:ident => $visit:ident($zero:expr)),* $(,)?) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                visitor.$visit($zero)
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for Sampler<'_> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_bool(false)
+    }
+
+    sample_number! {
+        deserialize_i8 => visit_i8(0),
+        deserialize_i16 => visit_i16(0),
+        deserialize_i32 => visit_i32(0),
+        deserialize_i64 => visit_i64(0),
+        deserialize_i128 => visit_i128(0),
+        deserialize_u8 => visit_u8(0),
+        deserialize_u16 => visit_u16(0),
+        deserialize_u32 => visit_u32(0),
+        deserialize_u64 => visit_u64(0),
+        deserialize_u128 => visit_u128(0),
+        deserialize_f32 => visit_f32(0.0),
+        deserialize_f64 => visit_f64(0.0),
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_char('a')
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_str("")
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_str("")
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_bytes(&[])
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_bytes(&[])
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_none()
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        mut self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self.nested()?)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_tuple(0, visitor)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_seq(Elements {
+            remaining: len,
+            sampler: self,
+        })
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_struct("", &[], visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.names.fields.extend(fields);
+        visitor.visit_map(Fields {
+            fields: fields.iter(),
+            sampler: self,
+        })
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.names.variants.extend(variants);
+        let variant = *variants
+            .first()
+            .ok_or_else(|| Error::custom("the enum has no variants"))?;
+        visitor.visit_enum(Variant {
+            variant,
+            sampler: self,
+        })
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_str("")
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+}
+
+/// The elements of a sequence or tuple built by a [`Sampler`].
+struct Elements<'a> {
+    remaining: usize,
+    sampler: Sampler<'a>,
+}
+
+impl<'de> SeqAccess<'de> for Elements<'_> {
+    type Error = Error;
+
+    fn next_element_seed<S: DeserializeSeed<'de>>(
+        &mut self,
+        seed: S,
+    ) -> Result<Option<S::Value>, Error> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        seed.deserialize(self.sampler.nested()?).map(Some)
+    }
+}
+
+/// The fields of a struct built by a [`Sampler`].
+struct Fields<'a> {
+    fields: std::slice::Iter<'static, &'static str>,
+    sampler: Sampler<'a>,
+}
+
+impl<'de> MapAccess<'de> for Fields<'_> {
+    type Error = Error;
+
+    fn next_key_seed<S: DeserializeSeed<'de>>(
+        &mut self,
+        seed: S,
+    ) -> Result<Option<S::Value>, Error> {
+        self.fields
+            .next()
+            .map(|field| seed.deserialize(field.into_deserializer()))
+            .transpose()
+    }
+
+    fn next_value_seed<S: DeserializeSeed<'de>>(&mut self, seed: S) -> Result<S::Value, Error> {
+        seed.deserialize(self.sampler.nested()?)
+    }
+}
+
+/// The variant of an enum built by a [`Sampler`].
+struct Variant<'a> {
+    variant: &'static str,
+    sampler: Sampler<'a>,
+}
+
+impl<'de> EnumAccess<'de> for Variant<'_> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<S: DeserializeSeed<'de>>(self, seed: S) -> Result<(S::Value, Self), Error> {
+        let variant = seed.deserialize(self.variant.into_deserializer())?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for Variant<'_> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<S: DeserializeSeed<'de>>(mut self, seed: S) -> Result<S::Value, Error> {
+        seed.deserialize(self.sampler.nested()?)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Error> {
+        self.sampler.deserialize_tuple(len, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.sampler.deserialize_struct("", fields, visitor)
+    }
+}